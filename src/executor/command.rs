@@ -2,19 +2,49 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::executor::generator::GenOptions;
+
 #[derive(Debug, Parser)]
 #[command(help_template("{tab}{subcommands}"))]
 pub enum Command {
-    /// List nodes in the group or print entry.
+    /// List numbered nodes in the group or print entry.
+    /// Listed nodes (and results of `find` and `ff`) can be
+    /// referred by number in other commands, e.g. `show #2`.
     #[command(name = "ls")]
     ListDir {
         #[arg(default_value_t = String::from(""), value_hint=clap::ValueHint::Other)]
         path: String,
     },
+    /// Print group hierarchy as a tree, starting at current group
+    /// or group with given path. Every group shows number of entries
+    /// it contains, including entries in its subgroups.
+    #[command(name = "tree")]
+    Tree {
+        /// Descend at most this many levels.
+        #[arg(short = 'L', long, value_parser = clap::value_parser!(u32).range(1..))]
+        level: Option<u32>,
+
+        /// List entries together with groups (default).
+        #[arg(short, long, overrides_with = "groups_only")]
+        entries: bool,
+
+        /// List only groups.
+        #[arg(short = 'd', long, overrides_with = "entries")]
+        groups_only: bool,
+
+        /// Print total number of groups and entries at the end.
+        #[arg(short, long)]
+        summary: bool,
+
+        /// Relative path to group.
+        #[arg(default_value_t = String::from(""), value_hint=clap::ValueHint::Other)]
+        path: String,
+    },
     /// Change current group to given one.
+    /// Without path goes to root group, `cd -` returns to previous group.
     #[command(name = "cd")]
     ChangeDir {
-        // Relative path of group. Must not be entry!
+        // Relative or absolute (starting with /) path of group. Must not be entry!
         #[arg(default_value_t = String::from(""), value_hint=clap::ValueHint::Other)]
         path: String,
     },
@@ -65,30 +95,269 @@ pub enum Command {
         entry: String,
     },
 
+    /// Create new entry in current group or in group with given path.
+    /// Entry fields are read interactively.
+    #[command(name = "new")]
+    NewEntry {
+        /// Relative path to group.
+        #[arg(default_value_t = String::from(""), value_hint=clap::ValueHint::Other)]
+        path: String,
+    },
+
+    /// Edit fields of an existing entry.
+    /// Every field is prompted with its current value,
+    /// protected fields are kept if left empty.
+    #[command(name = "edit")]
+    EditEntry {
+        /// Relative path to entry.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        entry: String,
+    },
+
+    /// Edit entry as TOML document in $VISUAL or $EDITOR.
+    /// Changes are shown and must be confirmed before applying.
+    #[command(name = "vi")]
+    EditEntryInEditor {
+        /// Relative path to entry.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        entry: String,
+    },
+
+    /// Remove entry, or group together with its content.
+    /// Nodes are moved to recycle bin if it's enabled,
+    /// nodes already in recycle bin are deleted permanently.
+    #[command(name = "rm")]
+    Remove {
+        /// Remove groups and their content recursively.
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Delete permanently instead of moving to recycle bin.
+        #[arg(short, long)]
+        force: bool,
+
+        /// Relative path to entry or group.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        path: String,
+    },
+
+    /// Move or rename entry or group.
+    /// If destination is an existing group node is moved into it,
+    /// otherwise node is moved into parent of destination and renamed.
+    #[command(name = "mv")]
+    Move {
+        /// Relative path to entry or group.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        source: String,
+
+        /// Relative path to destination group or new name.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        destination: String,
+    },
+
+    /// Create new group.
+    #[command(name = "mkdir")]
+    MakeDir {
+        /// Create missing intermediate groups,
+        /// no error if group already exists.
+        #[arg(short, long)]
+        parents: bool,
+
+        /// Relative path of new group.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        path: String,
+    },
+
+    /// Remove group, only if it's empty.
+    #[command(name = "rmdir")]
+    RemoveDir {
+        /// Relative path to group.
+        #[arg(value_hint=clap::ValueHint::Other)]
+        path: String,
+    },
+
+    /// Search entries in the whole database and print their full paths.
+    /// Title, username, URL, notes and custom fields are searched,
+    /// protected values never are.
+    #[command(name = "find")]
+    Find {
+        /// Treat term as regular expression instead of
+        /// case-insensitive substring.
+        #[arg(long, short)]
+        regex: bool,
+
+        /// Text or regular expression to search for.
+        term: String,
+    },
+
+    /// Fuzzy search entries by full path (which ends with title),
+    /// best matches are printed first with matched characters highlighted.
+    /// Titles with a typo or two are matched too.
+    #[command(name = "ff")]
+    FuzzyFind {
+        /// Maximum number of results.
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: usize,
+
+        /// Characters to search for, in order but not necessarily adjacent.
+        /// Search is case-insensitive unless query contains uppercase letters.
+        query: String,
+    },
+
+    /// Generate random password or passphrase (with --words).
+    /// Uses uppercase, lowercase, digits and symbols
+    /// if no character class is chosen.
+    /// Can also be used at password prompt by typing "!gen [options]".
+    #[command(name = "gen")]
+    Generate {
+        #[command(flatten)]
+        options: GenOptions,
+
+        /// Copy password to clipboard using OSC52 instead of printing it.
+        #[arg(long, short)]
+        copy: bool,
+    },
+
     /// Clear clipboard using OSC52 ANSI escape sequence.
     /// Not all terminals support this!
     #[command(name = "cx")]
     ClearClipboard,
 
     /// Open database with given file path and password.
+    /// Password is read interactively if not given.
     /// Not allowed if database is another already opened.
     #[command(name = "open")]
     OpenDB {
         // Absolute or relative path to database
         #[arg(value_hint=clap::ValueHint::FilePath)]
         path: PathBuf,
-        // Password for given database
-        password: String,
+        // Password for given database.
+        // Avoid passing it here, it's visible in shell history.
+        password: Option<String>,
+
+        /// Key file used together with (or instead of) password.
+        /// Use empty password for databases protected only by key file.
+        #[arg(long, short, value_hint=clap::ValueHint::FilePath)]
+        keyfile: Option<PathBuf>,
+    },
+
+    /// Create new database with given file path and open it.
+    /// Password is read interactively.
+    /// Not allowed if database is another already opened.
+    #[command(name = "create")]
+    CreateDB {
+        // Absolute or relative path of new database
+        #[arg(value_hint=clap::ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Key file used together with (or instead of) password.
+        #[arg(long, short, value_hint=clap::ValueHint::FilePath)]
+        keyfile: Option<PathBuf>,
     },
 
     /// Close currently opened database.
+    /// Refuses to close database with unsaved changes unless forced.
     #[command(name = "close")]
-    CloseDB,
+    CloseDB {
+        /// Discard unsaved changes.
+        #[arg(long, short)]
+        force: bool,
+    },
+
+    /// Print command history of currently opened database.
+    /// Commands carrying secrets and commands starting
+    /// with space are never stored in history.
+    #[command(name = "history")]
+    History {
+        /// Clear history, both in memory and on disk.
+        #[arg(long, short)]
+        clear: bool,
+    },
+
+    /// Save currently opened database to the file it was opened from.
+    #[command(name = "save")]
+    Save,
+
+    /// Save currently opened database to given file.
+    /// Subsequent saves will write to that file.
+    #[command(name = "save-as")]
+    SaveAs {
+        // Absolute or relative path of new database file
+        #[arg(value_hint=clap::ValueHint::FilePath)]
+        path: PathBuf,
+    },
+}
+
+/// Split command line into words like shell does, except that `#` doesn't
+/// start a comment so that previous results can be referred as `#N`
+/// and `\/` (and trailing `\`) is kept as is so that names can contain slashes.
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut escaped = String::with_capacity(input.len());
+    let mut quote = None;
+    let mut word_start = true;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '#') if word_start => escaped.push('\\'),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+        escaped.push(c);
+        word_start = quote.is_none() && c.is_whitespace();
+        if c == '\\' && quote != Some('\'') {
+            let next = chars.next();
+            match next {
+                // keep escaped slash for path resolution, see `path` module
+                Some('/') if quote.is_none() => escaped.push_str("\\\\"),
+                // trailing backslash is taken literally
+                None if quote.is_none() => escaped.push('\\'),
+                _ => {}
+            }
+            escaped.extend(next);
+        }
+    }
+    shlex::split(&escaped).unwrap_or_default()
 }
 
 impl Command {
     pub fn try_parse(input: &str) -> Result<Self, clap::Error> {
-        let words = shlex::split(input).unwrap_or_default();
+        let words = split_words(input);
         Self::try_parse_from([String::new()].into_iter().chain(words))
     }
+
+    /// Whether command line carries secret values
+    /// and therefore must not be stored in history.
+    pub fn contains_secret(&self) -> bool {
+        matches!(
+            self,
+            Command::OpenDB {
+                password: Some(_),
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words_keeps_references_and_escapes() {
+        assert_eq!(split_words("show #2"), ["show", "#2"]);
+        assert_eq!(split_words("ls a#b"), ["ls", "a#b"]);
+        assert_eq!(split_words(r"ls a\/b"), ["ls", r"a\/b"]);
+        assert_eq!(split_words(r"ls a\\b"), ["ls", r"a\b"]);
+        assert_eq!(split_words(r"ls a\"), ["ls", r"a\"]);
+        assert_eq!(split_words(r"ls a\ b"), ["ls", "a b"]);
+    }
+
+    #[test]
+    fn split_words_quoted() {
+        assert_eq!(split_words(r#"ls "a\/b""#), ["ls", r"a\/b"]);
+        assert_eq!(split_words(r"ls 'a\/b'"), ["ls", r"a\/b"]);
+        assert_eq!(split_words("ls 'x y'/z"), ["ls", "x y/z"]);
+        assert_eq!(split_words("ls '#2'"), ["ls", "#2"]);
+    }
 }
@@ -1,19 +1,33 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ops::Range;
 
 use clap::CommandFactory;
 use fst::{automaton::Str, Automaton, IntoStreamer};
-use keepass::db::NodeRef;
+use keepass::db::{Node, NodeRef};
 use keepass::Database;
 use rustyline::{
-    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Helper,
+    completion::{Completer, Pair},
+    highlight::Highlighter,
+    hint::{Hinter, HistoryHinter},
+    validate::Validator,
+    Helper,
 };
 use uuid::Uuid;
 
+use crate::executor::command::split_words;
 use crate::executor::get_all_prefixes_under_group;
+use crate::executor::path;
 use crate::executor::Command;
 
+const COLOR_COMMAND: &str = "\x1b[1;34m";
+const COLOR_FLAG: &str = "\x1b[36m";
+const COLOR_EXISTING: &str = "\x1b[32m";
+const COLOR_ERROR: &str = "\x1b[31m";
+const COLOR_HINT: &str = "\x1b[90m";
+const COLOR_RESET: &str = "\x1b[0m";
+
 pub struct PasswordInput;
 
 pub struct EditorHelper {
@@ -22,6 +36,9 @@ pub struct EditorHelper {
     cmd_flags_to_arg: HashMap<String, HashMap<String, clap::Arg>>,
     dir_stack: Vec<Uuid>,
     db_entries: HashMap<Uuid, fst::Set<Vec<u8>>>,
+    // Child groups of each group by name (and `@<uuid-prefix>` if duplicate)
+    db_groups: HashMap<Uuid, HashMap<String, Uuid>>,
+    db_parents: HashMap<Uuid, Uuid>,
     db_root: Uuid,
 }
 
@@ -59,12 +76,16 @@ impl EditorHelper {
             cmd_flags_to_arg,
             dir_stack: Vec::new(),
             db_entries: HashMap::new(),
+            db_groups: HashMap::new(),
+            db_parents: HashMap::new(),
             db_root: Uuid::nil(),
         }
     }
 
     pub fn create_db_entries(&mut self, db: &Database) {
         self.db_entries.clear();
+        self.db_groups.clear();
+        self.db_parents.clear();
         self.db_root = db.root.uuid;
         for node in &db.root {
             if let NodeRef::Group(g) = node {
@@ -72,6 +93,19 @@ impl EditorHelper {
                 all_entries.sort();
                 let all_entries_trie = fst::Set::from_iter(&all_entries).unwrap();
                 self.db_entries.insert(g.uuid, all_entries_trie);
+
+                let duplicates = path::duplicate_names(g);
+                let mut groups = HashMap::new();
+                for child in &g.children {
+                    if let Node::Group(child) = child {
+                        if duplicates.contains(child.name.as_str()) {
+                            groups.insert(path::uuid_reference(child.uuid), child.uuid);
+                        }
+                        groups.insert(child.name.clone(), child.uuid);
+                        self.db_parents.insert(child.uuid, g.uuid);
+                    }
+                }
+                self.db_groups.insert(g.uuid, groups);
             }
         }
     }
@@ -79,6 +113,8 @@ impl EditorHelper {
     pub fn clear_db(&mut self) {
         self.dir_stack.clear();
         self.db_entries.clear();
+        self.db_groups.clear();
+        self.db_parents.clear();
     }
 
     pub fn set_dir_stack(&mut self, dir_stack: Vec<Uuid>) {
@@ -129,34 +165,230 @@ impl EditorHelper {
         }
     }
 
-    fn find_positional_args(&self, cmd: &str, prefix: &str) -> Vec<String> {
+    fn find_positional_args(&self, cmd: &str, word: &str) -> Vec<Pair> {
         let cmd = if let Some(cmd) = self.cmds.get(cmd) {
             cmd
         } else {
             return Vec::new();
         };
-        let mut result = Vec::new();
+        let mut hints: Vec<clap::ValueHint> = Vec::new();
         for arg in cmd.get_arguments().filter(|arg| arg.is_positional()) {
-            match arg.get_value_hint() {
-                clap::ValueHint::Other => {
-                    // this is entry in keepass database
-                    // get database entries here
-                    let curr_dir = self.dir_stack.last().unwrap_or(&self.db_root);
-                    let res = self
-                        .db_entries
-                        .get(curr_dir)
-                        .unwrap()
-                        .search(Str::new(prefix).starts_with())
-                        .into_stream()
-                        .into_strs()
-                        .unwrap();
-                    result.extend(res);
+            if !hints.contains(&arg.get_value_hint()) {
+                hints.push(arg.get_value_hint());
+            }
+        }
+        hints
+            .into_iter()
+            .flat_map(|hint| self.complete_value(hint, word))
+            .collect()
+    }
+
+    fn complete_value(&self, hint: clap::ValueHint, word: &str) -> Vec<Pair> {
+        match hint {
+            // this is entry in keepass database
+            clap::ValueHint::Other => self.complete_db_path(word),
+            clap::ValueHint::FilePath | clap::ValueHint::AnyPath => complete_file_path(word, false),
+            clap::ValueHint::DirPath => complete_file_path(word, true),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Group which path (split into escaped segments) leads to.
+    fn find_group(&self, segments: &[&str]) -> Option<Uuid> {
+        let (mut group, segments) = match segments.split_first() {
+            Some((&"", rest)) | Some((&"~", rest)) if !rest.is_empty() => (self.db_root, rest),
+            _ => (*self.dir_stack.last().unwrap_or(&self.db_root), segments),
+        };
+        for segment in segments {
+            match *segment {
+                "" | "." => {}
+                ".." => group = *self.db_parents.get(&group).unwrap_or(&self.db_root),
+                name => {
+                    group = *self
+                        .db_groups
+                        .get(&group)?
+                        .get(&path::unescape_name(name))?
                 }
-                _ => {}
             }
         }
-        result
+        Some(group)
+    }
+
+    /// Whether path in database exists, `None` if it can't be told
+    /// (no database opened, references to results or by UUID).
+    fn db_path_exists(&self, word: &str) -> Option<bool> {
+        let is_reference = word.starts_with('#')
+            || word.starts_with('@')
+            || word.contains("/@")
+            || word == "-"
+            || word.chars().all(|c| c.is_ascii_digit());
+        if self.db_entries.is_empty() || word.is_empty() || is_reference {
+            return None;
+        }
+        let segments = path::split_escaped(word);
+        let (name, parents) = segments.split_last().unwrap();
+        let group = match self.find_group(parents) {
+            Some(group) => group,
+            None => return Some(false),
+        };
+        match *name {
+            "" | "." | ".." => Some(true),
+            name => Some(
+                self.db_entries
+                    .get(&group)?
+                    .contains(path::unescape_name(name)),
+            ),
+        }
     }
+
+    /// Complete last segment of (possibly nested) path,
+    /// group candidates end with `/` so that completion can continue.
+    fn complete_db_path(&self, word: &str) -> Vec<Pair> {
+        let segments = path::split_escaped(word);
+        let (prefix, parents) = segments.split_last().unwrap();
+        let parent_path = &word[..word.len() - prefix.len()];
+        let group = match self.find_group(parents) {
+            Some(group) => group,
+            None => return Vec::new(),
+        };
+        if *prefix == ".." {
+            return vec![Pair {
+                display: "../".to_string(),
+                replacement: format!("{}../", parent_path),
+            }];
+        }
+        let names = match self.db_entries.get(&group) {
+            Some(names) => names
+                .search(Str::new(&path::unescape_name(prefix)).starts_with())
+                .into_stream()
+                .into_strs()
+                .unwrap(),
+            None => return Vec::new(),
+        };
+        let groups = self.db_groups.get(&group);
+        names
+            .into_iter()
+            .map(|name| {
+                let mut display = path::escape_name(&name);
+                if groups.is_some_and(|groups| groups.contains_key(&name)) {
+                    display.push('/');
+                }
+                Pair {
+                    replacement: format!("{}{}", parent_path, display),
+                    display,
+                }
+            })
+            .collect()
+    }
+}
+
+fn home_dir() -> Option<String> {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok()
+}
+
+/// Complete path in file system, directories end with `/`.
+/// Leading `~` is expanded to home directory, hidden files are
+/// offered only if name starts with `.` and KeePass databases come first.
+fn complete_file_path(word: &str, dirs_only: bool) -> Vec<Pair> {
+    let word = match (word.strip_prefix('~'), home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!(
+                "{}{}",
+                home.trim_end_matches('/'),
+                if rest.is_empty() { "/" } else { rest }
+            )
+        }
+        _ => word.to_string(),
+    };
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word.as_str()),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates: Vec<(bool, String)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let mut name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            // follows symlinks so that linked directories can be entered
+            let is_dir = entry.path().is_dir();
+            if dirs_only && !is_dir {
+                return None;
+            }
+            let is_database = !is_dir && name.ends_with(".kdbx");
+            if is_dir {
+                name.push('/');
+            }
+            Some((!is_database, name))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .map(|(_, name)| Pair {
+            replacement: format!("{}{}", dir, name),
+            display: name,
+        })
+        .collect()
+}
+
+/// Byte ranges of words in line,
+/// whitespace inside quotes or escaped doesn't separate words.
+fn word_spans(line: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        let separates = quote.is_none() && !escaped && c.is_whitespace();
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, _) => {}
+        }
+        if separates {
+            if let Some(start) = start.take() {
+                spans.push(start..i);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        spans.push(start..line.len());
+    }
+    spans
+}
+
+/// Byte index where last word of line starts.
+fn last_word_start(line: &str) -> usize {
+    match word_spans(line).last() {
+        Some(span) if span.end == line.len() => span.start,
+        _ => line.len(),
+    }
+}
+
+/// Candidates which are displayed the same way they are inserted.
+fn pairs(candidates: Vec<String>) -> Vec<Pair> {
+    candidates
+        .into_iter()
+        .map(|candidate| Pair {
+            display: candidate.clone(),
+            replacement: candidate,
+        })
+        .collect()
 }
 
 impl Highlighter for PasswordInput {
@@ -181,7 +413,71 @@ impl Completer for PasswordInput {
 
 impl Helper for PasswordInput {}
 
-impl Highlighter for EditorHelper {}
+impl Highlighter for EditorHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let spans = word_spans(line);
+        let cmd = match spans.first() {
+            Some(span) => &line[span.clone()],
+            None => return Cow::Borrowed(line),
+        };
+        let mut colors = vec![if self.cmds.contains_key(cmd) {
+            COLOR_COMMAND
+        } else {
+            COLOR_ERROR
+        }];
+        if let Some(command) = self.cmds.get(cmd) {
+            let flags_to_arg = self.cmd_flags_to_arg.get(cmd);
+            let mut positionals = command.get_arguments().filter(|arg| arg.is_positional());
+            let mut option_value = false;
+            for span in &spans[1..] {
+                let raw = &line[span.clone()];
+                let word = split_words(raw).pop().unwrap_or_default();
+                let color = if option_value {
+                    option_value = false;
+                    None
+                } else if raw.starts_with('-') && raw.len() > 1 {
+                    option_value = flags_to_arg
+                        .and_then(|flags| flags.get(raw.trim_start_matches('-')))
+                        .is_some_and(|arg| arg.get_action().takes_values());
+                    Some(COLOR_FLAG)
+                } else {
+                    match positionals.next().map(clap::Arg::get_value_hint) {
+                        Some(clap::ValueHint::Other) => match self.db_path_exists(&word) {
+                            Some(true) => Some(COLOR_EXISTING),
+                            Some(false) => Some(COLOR_ERROR),
+                            None => None,
+                        },
+                        _ => None,
+                    }
+                };
+                colors.push(color.unwrap_or(""));
+            }
+        }
+
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        let mut end = 0;
+        for (span, color) in spans.iter().zip(colors) {
+            highlighted.push_str(&line[end..span.start]);
+            if color.is_empty() {
+                highlighted.push_str(&line[span.clone()]);
+            } else {
+                highlighted.push_str(&format!("{}{}{}", color, &line[span.clone()], COLOR_RESET));
+            }
+            end = span.end;
+        }
+        highlighted.push_str(&line[end..]);
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{}{}{}", COLOR_HINT, hint, COLOR_RESET))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // every change can alter colors of words
+        true
+    }
+}
 
 impl Validator for EditorHelper {
     fn validate(
@@ -203,14 +499,30 @@ impl Validator for EditorHelper {
 impl Hinter for EditorHelper {
     type Hint = String;
 
+    /// Suggest rest of the most recent matching history entry,
+    /// or of the only completion candidate.
     fn hint(&self, line: &str, pos: usize, ctx: &rustyline::Context<'_>) -> Option<Self::Hint> {
-        let _ = (line, pos, ctx);
-        None
+        if line.trim().is_empty() || pos < line.len() {
+            return None;
+        }
+        let history_hint = HistoryHinter {}.hint(line, pos, ctx);
+        if let Some(hint) = history_hint {
+            return Some(hint);
+        }
+        let (start, candidates) = self.complete(line, pos, ctx).ok()?;
+        match candidates.as_slice() {
+            [candidate] => {
+                let quoted = shlex::quote(&candidate.replacement);
+                let rest = quoted.strip_prefix(&line[start..pos])?;
+                (!rest.is_empty()).then(|| rest.to_string())
+            }
+            _ => None,
+        }
     }
 }
 
 impl Completer for EditorHelper {
-    type Candidate = String;
+    type Candidate = Pair;
 
     fn complete(
         &self, // FIXME should be `&mut self`
@@ -220,12 +532,12 @@ impl Completer for EditorHelper {
     ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
         if pos == 0 {
             // completing all cmds
-            return Ok((0, self.cmds.keys().map(String::from).collect()));
+            return Ok((0, pairs(self.cmds.keys().map(String::from).collect())));
         } else if pos != line.len() {
             // won't complete if not at last position in line
             return Ok((0, Vec::new()));
         }
-        let words = shlex::split(line).unwrap_or_default();
+        let words = split_words(line);
         if words.is_empty() {
             return Ok((0, Vec::new()));
         }
@@ -237,7 +549,7 @@ impl Completer for EditorHelper {
                 // don't autocomplete if user is moved from typing command
                 return Ok((pos, self.find_positional_args(cmd, "")));
             }
-            return Ok((0, self.find_cmds_starting_with(word)));
+            return Ok((0, pairs(self.find_cmds_starting_with(word))));
         }
         let last = &words[words.len() - 1].trim();
 
@@ -268,17 +580,31 @@ impl Completer for EditorHelper {
             };
             return Ok((
                 pos,
-                self.find_non_positional_args(cmd, prefix, is_short, existing_flags),
+                pairs(self.find_non_positional_args(cmd, prefix, is_short, existing_flags)),
             ));
         }
-        // positional arg
-        let res = self.find_positional_args(cmd, last);
-        Ok((pos - last.len(), res))
+        // positional arg or option value,
+        // completed as a whole word so that quotes can be added
+        let start = last_word_start(line);
+        let word = split_words(&line[start..]).pop().unwrap_or_default();
+        let preceding = split_words(&line[..start]);
+        let option = preceding
+            .last()
+            .filter(|flag| preceding.len() > 1 && flag.starts_with('-'))
+            .and_then(|flag| {
+                self.cmd_flags_to_arg
+                    .get(cmd)?
+                    .get(flag.trim_start_matches('-'))
+            });
+        if let Some(option) = option.filter(|arg| arg.get_action().takes_values()) {
+            return Ok((start, self.complete_value(option.get_value_hint(), &word)));
+        }
+        let res = self.find_positional_args(cmd, &word);
+        Ok((start, res))
     }
 
     fn update(&self, line: &mut rustyline::line_buffer::LineBuffer, start: usize, elected: &str) {
         let quoted = shlex::quote(elected);
-        eprintln!("Updating line with {}", quoted);
         let end = line.pos();
         line.replace(start..end, quoted.as_ref());
     }
@@ -0,0 +1,325 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::NaiveDateTime;
+use keepass::db::{Entry, Value};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::executor::{prompt, STANDARD_FIELDS};
+
+const EXPIRY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const DOCUMENT_HEADER: &str = "\
+# Edit entry below, save and quit editor to apply changes.
+# Fields can be added or removed by adding or removing [[field]] tables.
+# Expiry time format is YYYY-MM-DD HH:MM:SS.
+
+";
+
+/// Entry rendered as TOML document for editing in external editor.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct EntryDocument {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    expires: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expiry_time: Option<String>,
+    #[serde(default, rename = "field")]
+    fields: Vec<FieldDocument>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FieldDocument {
+    name: String,
+    value: String,
+    #[serde(default)]
+    protected: bool,
+}
+
+impl EntryDocument {
+    fn from_entry(entry: &Entry) -> Self {
+        let mut custom_fields: Vec<&String> = entry
+            .fields
+            .keys()
+            .filter(|name| !STANDARD_FIELDS.contains(&name.as_str()))
+            .collect();
+        custom_fields.sort();
+
+        let standard_fields = STANDARD_FIELDS.iter().map(|name| {
+            let value = entry.fields.get(*name).cloned().unwrap_or_else(|| {
+                if *name == "Password" {
+                    Value::Protected("".as_bytes().into())
+                } else {
+                    Value::Unprotected(String::new())
+                }
+            });
+            (name.to_string(), value)
+        });
+        let custom_fields = custom_fields
+            .into_iter()
+            .map(|name| (name.clone(), entry.fields[name].clone()));
+
+        let fields = standard_fields
+            .chain(custom_fields)
+            .filter_map(|(name, value)| match value {
+                // binary fields can't be represented in text, they are left untouched
+                Value::Bytes(_) => None,
+                Value::Unprotected(value) => Some(FieldDocument {
+                    name,
+                    value,
+                    protected: false,
+                }),
+                Value::Protected(value) => Some(FieldDocument {
+                    name,
+                    value: String::from_utf8_lossy(value.unsecure()).into_owned(),
+                    protected: true,
+                }),
+            })
+            .collect();
+
+        Self {
+            tags: entry.tags.clone(),
+            expires: entry.times.expires,
+            expiry_time: entry
+                .times
+                .get_expiry()
+                .map(|time| time.format(EXPIRY_TIME_FORMAT).to_string()),
+            fields,
+        }
+    }
+
+    /// Create copy of entry with values from this document.
+    fn apply(&self, entry: &Entry) -> Result<Entry, String> {
+        let mut new_entry = entry.clone();
+        new_entry
+            .fields
+            .retain(|_, value| matches!(value, Value::Bytes(_)));
+        for field in &self.fields {
+            if field.name.is_empty() {
+                return Err("Field name must not be empty!".to_string());
+            }
+            if field.value.is_empty()
+                && STANDARD_FIELDS.contains(&field.name.as_str())
+                && !entry.fields.contains_key(&field.name)
+            {
+                // standard fields are always rendered, don't add them if left empty
+                continue;
+            }
+            if new_entry.fields.contains_key(&field.name) {
+                return Err(format!("Field {} is defined more than once!", field.name));
+            }
+            let value = if field.protected {
+                Value::Protected(field.value.as_bytes().into())
+            } else {
+                Value::Unprotected(field.value.clone())
+            };
+            new_entry.fields.insert(field.name.clone(), value);
+        }
+        if new_entry.get_title().unwrap_or("").is_empty() {
+            return Err("Title must not be empty!".to_string());
+        }
+
+        new_entry.tags = self.tags.clone();
+        new_entry.times.expires = self.expires;
+        if let Some(expiry_time) = &self.expiry_time {
+            let expiry_time = NaiveDateTime::parse_from_str(expiry_time, EXPIRY_TIME_FORMAT)
+                .map_err(|err| format!("Invalid expiry time {}: {}", expiry_time, err))?;
+            new_entry.times.set_expiry(expiry_time);
+        } else if self.expires {
+            return Err("Expiring entry must have expiry_time!".to_string());
+        }
+        Ok(new_entry)
+    }
+}
+
+fn describe_value(value: Option<&Value>) -> String {
+    match value {
+        None => "(not set)".to_string(),
+        Some(Value::Bytes(_)) => "(bytes)".to_string(),
+        Some(Value::Unprotected(value)) => format!("{:?}", value),
+        Some(Value::Protected(_)) => "*** SECRET ***".to_string(),
+    }
+}
+
+/// Human readable list of changes between two versions of entry.
+/// Protected values are never printed.
+fn diff_entries(old: &Entry, new: &Entry) -> Vec<String> {
+    let mut names: Vec<&String> = old.fields.keys().chain(new.fields.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut diff = Vec::new();
+    for name in names {
+        let (old_value, new_value) = (old.fields.get(name), new.fields.get(name));
+        if old_value == new_value {
+            continue;
+        }
+        match (old_value, new_value) {
+            (Some(Value::Protected(_)), Some(Value::Protected(_))) => {
+                diff.push(format!("~ {}: protected value changed", name));
+            }
+            _ => {
+                if old_value.is_some() {
+                    diff.push(format!("- {}: {}", name, describe_value(old_value)));
+                }
+                if new_value.is_some() {
+                    diff.push(format!("+ {}: {}", name, describe_value(new_value)));
+                }
+            }
+        }
+    }
+    if old.tags != new.tags {
+        diff.push(format!("- tags: {:?}", old.tags));
+        diff.push(format!("+ tags: {:?}", new.tags));
+    }
+    if old.times.expires != new.times.expires {
+        diff.push(format!("- expires: {}", old.times.expires));
+        diff.push(format!("+ expires: {}", new.times.expires));
+    }
+    if old.times.get_expiry() != new.times.get_expiry() {
+        let format_time = |time: Option<&NaiveDateTime>| {
+            time.map(|time| time.format(EXPIRY_TIME_FORMAT).to_string())
+                .unwrap_or("(not set)".to_string())
+        };
+        diff.push(format!(
+            "- expiry_time: {}",
+            format_time(old.times.get_expiry())
+        ));
+        diff.push(format!(
+            "+ expiry_time: {}",
+            format_time(new.times.get_expiry())
+        ));
+    }
+    diff
+}
+
+/// Overwrite file contents with zeros before unlinking it,
+/// so that secrets don't linger on disk.
+fn scrub_file(path: &Path) {
+    if let Ok(metadata) = fs::metadata(path) {
+        if let Ok(mut file) = OpenOptions::new().write(true).open(path) {
+            let _ = file.write_all(&vec![0; metadata.len() as usize]);
+            let _ = file.sync_all();
+        }
+    }
+    let _ = fs::remove_file(path);
+}
+
+/// Directory accessible only by current user, removed with its content on drop.
+struct PrivateDir {
+    path: PathBuf,
+}
+
+impl PrivateDir {
+    fn new() -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("kpcli-{}", Uuid::new_v4().simple()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder
+            .create(&path)
+            .map_err(|err| format!("Error creating temporary directory: {}", err))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PrivateDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn run_editor(path: &Path) -> Result<(), String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    let words = shlex::split(&editor).unwrap_or_default();
+    let (program, args) = words
+        .split_first()
+        .ok_or(format!("Invalid editor command: {}", editor))?;
+    let status = process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|err| format!("Error running {}: {}", editor, err))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status));
+    }
+    Ok(())
+}
+
+fn write_document(path: &Path, content: &str) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|err| format!("Error writing {}: {}", path.display(), err))
+}
+
+fn edit_document(dir: &Path, entry: &Entry) -> Result<Option<Entry>, String> {
+    let path = dir.join("entry.toml");
+    let document = toml::to_string(&EntryDocument::from_entry(entry))
+        .map_err(|err| format!("Error rendering entry: {}", err))?;
+    let mut content = format!("{}{}", DOCUMENT_HEADER, document);
+    loop {
+        write_document(&path, &content)?;
+        run_editor(&path)?;
+        let edited = fs::read_to_string(&path)
+            .map_err(|err| format!("Error reading {}: {}", path.display(), err))?;
+        scrub_file(&path);
+
+        let result = toml::from_str::<EntryDocument>(&edited)
+            .map_err(|err| format!("{}", err))
+            .and_then(|document| document.apply(entry));
+        match result {
+            Ok(new_entry) => return Ok(Some(new_entry)),
+            Err(err) => {
+                eprintln!("{}", err);
+                let answer = prompt::read_line("Edit again? [Y/n]: ")?;
+                if answer.eq_ignore_ascii_case("n") {
+                    return Ok(None);
+                }
+                content = edited;
+            }
+        }
+    }
+}
+
+/// Let user edit entry as TOML document in `$VISUAL`/`$EDITOR`.
+/// Returns edited copy of entry if user confirmed changes.
+pub fn edit_entry_in_editor(entry: &Entry) -> Result<Option<Entry>, String> {
+    let dir = PrivateDir::new()?;
+    let result = edit_document(&dir.path, entry);
+    scrub_file(&dir.path.join("entry.toml"));
+    drop(dir);
+
+    let new_entry = match result? {
+        Some(new_entry) => new_entry,
+        None => return Ok(None),
+    };
+    let diff = diff_entries(entry, &new_entry);
+    if diff.is_empty() {
+        return Ok(None);
+    }
+    for line in diff {
+        println!("{}", line);
+    }
+    let answer = prompt::read_line("Apply changes? [y/N]: ")?;
+    if !answer.eq_ignore_ascii_case("y") {
+        return Ok(None);
+    }
+    Ok(Some(new_entry))
+}
@@ -0,0 +1,111 @@
+//! fzf-like fuzzy matching of query characters as subsequence of text.
+
+use fst::{automaton::Levenshtein, IntoStreamer, Set};
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+// Matching character right after separator, e.g. start of group or word
+const BONUS_BOUNDARY: i32 = 8;
+// Matching uppercase letter after lowercase one, e.g. "B" in "MyBank"
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+fn bonus_at(text: &[char], index: usize) -> i32 {
+    let current = text[index];
+    match index.checked_sub(1).map(|i| text[i]) {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() && current.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(_) => 0,
+    }
+}
+
+/// Score of best match of `query` as subsequence of `text` together with
+/// indices (in characters) of matched characters, `None` if it doesn't match.
+/// Matching is case-insensitive unless query contains uppercase letters.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let normalize = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let query: Vec<char> = query.chars().map(normalize).collect();
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().copied().map(normalize).collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // best[i][j] = (score, previous match position) of match
+    // where query[i] is matched with text[j]
+    let mut best: Vec<Vec<Option<(i32, usize)>>> = vec![vec![None; text.len()]; query.len()];
+    for (i, q) in query.iter().enumerate() {
+        for j in i..text.len() {
+            if text[j] != *q {
+                continue;
+            }
+            let bonus = bonus_at(&original, j);
+            if i == 0 {
+                best[i][j] = Some((SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER, j));
+                continue;
+            }
+            let mut candidate: Option<(i32, usize)> = None;
+            for (k, previous) in best[i - 1].iter().enumerate().take(j).skip(i - 1) {
+                let Some((score, _)) = *previous else {
+                    continue;
+                };
+                let score = if k + 1 == j {
+                    score + SCORE_MATCH + bonus.max(BONUS_CONSECUTIVE)
+                } else {
+                    let gap = (j - k - 1) as i32;
+                    score + SCORE_MATCH + bonus + SCORE_GAP_START + SCORE_GAP_EXTENSION * (gap - 1)
+                };
+                if candidate.is_none_or(|(best_score, _)| score > best_score) {
+                    candidate = Some((score, k));
+                }
+            }
+            best[i][j] = candidate;
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut position, (score, _)) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, m)| m.map(|m| (j, m)))
+        .max_by_key(|(_, (score, _))| *score)?;
+    let mut indices = vec![position];
+    for i in (1..=last).rev() {
+        position = best[i][position].unwrap().1;
+        indices.push(position);
+    }
+    indices.reverse();
+    Some((score, indices))
+}
+
+/// Words from `words` within small edit distance of `query`
+/// (1 for queries of 3 or 4 characters, 2 for longer ones).
+pub fn typo_matches(query: &str, words: &[String]) -> Vec<String> {
+    let query = query.to_lowercase();
+    let distance = match query.chars().count() {
+        0..=2 => return Vec::new(),
+        3..=4 => 1,
+        _ => 2,
+    };
+    let mut words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    words.sort();
+    words.dedup();
+    let (Ok(set), Ok(automaton)) = (Set::from_iter(words), Levenshtein::new(&query, distance))
+    else {
+        return Vec::new();
+    };
+    set.search(automaton)
+        .into_stream()
+        .into_strs()
+        .unwrap_or_default()
+}
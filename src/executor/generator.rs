@@ -0,0 +1,196 @@
+use clap::Parser;
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+// Characters easily confused with each other in some fonts
+const LOOK_ALIKE: &str = "0OoIl1|";
+// Word list compiled into binary, one word per line.
+// Diceware style lists with dice roll number before each word are accepted too.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Prefix which can be typed at password prompt to generate password instead.
+pub const GENERATE_PREFIX: &str = "!gen";
+
+#[derive(Debug, Parser)]
+#[command(name = GENERATE_PREFIX)]
+pub struct GenOptions {
+    /// Length of generated password.
+    #[arg(long, short, default_value_t = 20)]
+    pub length: usize,
+
+    /// Use uppercase letters.
+    #[arg(long, short = 'U')]
+    pub upper: bool,
+
+    /// Use lowercase letters.
+    #[arg(long, short = 'L')]
+    pub lower: bool,
+
+    /// Use digits.
+    #[arg(long, short)]
+    pub digits: bool,
+
+    /// Use ASCII symbols.
+    #[arg(long, short)]
+    pub symbols: bool,
+
+    /// Use extended ASCII (Latin-1) characters.
+    #[arg(long, short)]
+    pub extended: bool,
+
+    /// Exclude look-alike characters (0, O, o, I, l, 1, |).
+    #[arg(long, short = 'x')]
+    pub exclude_similar: bool,
+
+    /// Include at least one character of each used class.
+    #[arg(long, short = 'a')]
+    pub each_class: bool,
+
+    /// Generate passphrase of given number of words instead.
+    #[arg(
+        long,
+        short,
+        conflicts_with_all = ["upper", "lower", "digits", "symbols", "extended", "exclude_similar", "each_class"]
+    )]
+    pub words: Option<usize>,
+
+    /// Separator between passphrase words.
+    #[arg(long, default_value_t = String::from("-"), requires = "words")]
+    pub separator: String,
+
+    /// Capitalize each passphrase word.
+    #[arg(long, short = 'C', requires = "words")]
+    pub capitalize: bool,
+
+    /// Append random digit to passphrase.
+    #[arg(long, requires = "words")]
+    pub append_digit: bool,
+}
+
+impl GenOptions {
+    /// Character classes to use, upper, lower, digits and symbols if none were chosen.
+    fn classes(&self) -> Vec<Vec<char>> {
+        let extended: String = ('\u{a1}'..='\u{ff}')
+            .filter(|c| *c != '\u{ad}') // soft hyphen is invisible
+            .collect();
+        let selected = [
+            (self.upper, UPPER.to_string()),
+            (self.lower, LOWER.to_string()),
+            (self.digits, DIGITS.to_string()),
+            (self.symbols, SYMBOLS.to_string()),
+            (self.extended, extended),
+        ];
+        let classes: Vec<String> = if selected.iter().any(|(used, _)| *used) {
+            selected
+                .into_iter()
+                .filter_map(|(used, class)| used.then_some(class))
+                .collect()
+        } else {
+            [UPPER, LOWER, DIGITS, SYMBOLS].map(String::from).to_vec()
+        };
+        classes
+            .iter()
+            .map(|class| {
+                class
+                    .chars()
+                    .filter(|c| !self.exclude_similar || !LOOK_ALIKE.contains(*c))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+pub fn wordlist() -> Vec<&'static str> {
+    WORDLIST
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect()
+}
+
+fn generate_passphrase(options: &GenOptions, words: usize) -> Result<String, String> {
+    if words == 0 {
+        return Err("Number of words must be greater than 0!".to_string());
+    }
+    let wordlist = wordlist();
+    let mut rng = OsRng;
+    let words: Vec<String> = (0..words)
+        .map(|_| {
+            let word = *wordlist.choose(&mut rng).unwrap();
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) if options.capitalize => first.to_uppercase().chain(chars).collect(),
+                _ => word.to_string(),
+            }
+        })
+        .collect();
+    let mut passphrase = words.join(&options.separator);
+    if options.append_digit {
+        let digits: Vec<char> = DIGITS.chars().collect();
+        passphrase.push(*digits.choose(&mut rng).unwrap());
+    }
+    Ok(passphrase)
+}
+
+/// Entropy in bits of passwords generated with given options.
+/// "At least one of each class" constraint is not accounted for.
+pub fn entropy_bits(options: &GenOptions) -> f64 {
+    match options.words {
+        Some(words) => {
+            let digit_bits = if options.append_digit {
+                (DIGITS.len() as f64).log2()
+            } else {
+                0.0
+            };
+            words as f64 * (wordlist().len() as f64).log2() + digit_bits
+        }
+        None => options.length as f64 * (options.classes().concat().len() as f64).log2(),
+    }
+}
+
+pub fn generate_password(options: &GenOptions) -> Result<String, String> {
+    if let Some(words) = options.words {
+        return generate_passphrase(options, words);
+    }
+    let classes = options.classes();
+    if options.length == 0 {
+        return Err("Password length must be greater than 0!".to_string());
+    }
+    if options.each_class && options.length < classes.len() {
+        return Err(format!(
+            "Password length must be at least {} to include each character class!",
+            classes.len()
+        ));
+    }
+    let all: Vec<char> = classes.concat();
+    let mut rng = OsRng;
+    let mut password: Vec<char> = if options.each_class {
+        classes
+            .iter()
+            .map(|class| *class.choose(&mut rng).unwrap())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    while password.len() < options.length {
+        password.push(*all.choose(&mut rng).unwrap());
+    }
+    password.shuffle(&mut rng);
+    Ok(password.into_iter().collect())
+}
+
+/// If user typed `!gen [options]` at password prompt,
+/// generate password with given options.
+pub fn generate_from_prompt_input(input: &str) -> Option<Result<String, String>> {
+    if input != GENERATE_PREFIX && !input.starts_with(&format!("{} ", GENERATE_PREFIX)) {
+        return None;
+    }
+    let words = shlex::split(input).unwrap_or_default();
+    let result = GenOptions::try_parse_from(words)
+        .map_err(|err| format!("{}", err))
+        .and_then(|options| generate_password(&options));
+    Some(result)
+}
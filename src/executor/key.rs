@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use keepass::DatabaseKey;
+use sha2::{Digest, Sha256};
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Extract key from KeePass XML key file version 2.0.
+/// Key is stored as hex string with optional whitespace,
+/// `Hash` attribute holds first 4 bytes of its SHA-256.
+fn parse_xml_v2_keyfile(xml: &str) -> Option<Result<Vec<u8>, String>> {
+    if !xml.contains("<KeyFile>") || !xml.contains("<Version>2.0</Version>") {
+        return None;
+    }
+    let data_start = xml.find("<Data")?;
+    let data = &xml[data_start..];
+    let content_start = data.find('>')? + 1;
+    let content_end = data.find("</Data>")?;
+    let attributes = &data[..content_start];
+    let content: String = data
+        .get(content_start..content_end)?
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    let key = match decode_hex(&content) {
+        Some(key) if key.len() == 32 => key,
+        _ => return Some(Err("Invalid key data in XML key file!".to_string())),
+    };
+    if let Some(hash_start) = attributes.find("Hash=\"") {
+        let hash = &attributes[hash_start + 6..];
+        let hash = &hash[..hash.find('"').unwrap_or(hash.len())];
+        let digest = Sha256::digest(&key);
+        if decode_hex(hash).as_deref() != Some(&digest[..4]) {
+            return Some(Err("XML key file is corrupted (hash mismatch)!".to_string()));
+        }
+    }
+    Some(Ok(key))
+}
+
+/// Convert key file formats not handled by keepass crate
+/// (XML version 2.0 and 64 character hex) to raw 32 byte key.
+/// Other formats (XML version 1.0, raw 32 bytes, arbitrary file
+/// which is hashed) are passed as is.
+fn normalize_keyfile(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    if let Ok(text) = std::str::from_utf8(&data) {
+        if let Some(key) = parse_xml_v2_keyfile(text) {
+            return key;
+        }
+        if data.len() == 64 {
+            if let Some(key) = decode_hex(text) {
+                return Ok(key);
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// Build composite database key from password and/or key file.
+/// Empty password is ignored when key file is given,
+/// so that databases protected only by key file can be opened.
+pub fn build_key(password: Option<&str>, keyfile: Option<&Path>) -> Result<DatabaseKey, String> {
+    let mut key = DatabaseKey::new();
+    if let Some(password) = password.filter(|p| !p.is_empty() || keyfile.is_none()) {
+        key = key.with_password(password);
+    }
+    if let Some(keyfile) = keyfile {
+        let data = std::fs::read(keyfile)
+            .map_err(|err| format!("Error reading key file {}: {}", keyfile.display(), err))?;
+        let data = normalize_keyfile(data)?;
+        key = key
+            .with_keyfile(&mut data.as_slice())
+            .map_err(|err| format!("Error reading key file {}: {}", keyfile.display(), err))?;
+    }
+    Ok(key)
+}
@@ -1,27 +1,39 @@
 mod command;
 mod editor_helper;
+mod entry_document;
+mod fuzzy;
+mod generator;
+mod key;
+mod path;
+mod prompt;
 mod state;
+mod strength;
 
-use std::fs::File;
+use std::io::IsTerminal;
+use std::path::Path;
 
 pub use command::Command;
 pub use editor_helper::EditorHelper;
 pub use editor_helper::PasswordInput;
-use keepass::DatabaseKey;
 use keepass::{
-    db::{Entry, NodeRef, Value},
+    db::{Entry, Group, History, NodeRef, Times, Value},
     Database,
 };
+pub use key::build_key;
+use path::NodePath;
 pub use state::get_all_prefixes_under_group;
+use state::node_uuid;
+pub use state::Db;
 use state::State;
 use totp_rs::TOTP;
+use uuid::Uuid;
 
 pub struct Executor {
     state: State,
 }
 
 impl Executor {
-    pub fn new(db: Option<Database>) -> Self {
+    pub fn new(db: Option<Db>) -> Self {
         Self {
             state: State::new(db),
         }
@@ -38,6 +50,14 @@ impl Executor {
         self.state.db.as_ref().map(|db| &db.db)
     }
 
+    pub fn get_db_path(&self) -> Option<&Path> {
+        self.state.db.as_ref().map(|db| db.path.as_path())
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.state.db.as_ref().is_some_and(|db| db.dirty)
+    }
+
     pub fn execute(
         &mut self,
         command: Command,
@@ -50,26 +70,66 @@ impl Executor {
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                let group = db.get_current_group();
-                if let Some(node) = db.get_node(group, &path) {
-                    list_node(node);
+                if let Some(node) = path::resolve_node(db, &self.state.last_results, &path) {
+                    self.state.last_results = list_node(node);
                     Ok(())
                 } else {
                     Err(format!("{} does not exist!", path))
                 }
             }
+            Command::Tree {
+                level,
+                entries: _,
+                groups_only,
+                summary,
+                path,
+            } => {
+                let db = self
+                    .state
+                    .db
+                    .as_ref()
+                    .ok_or("Database not opened".to_string())?;
+                let group = match path::resolve_node(db, &self.state.last_results, &path) {
+                    Some(NodeRef::Group(g)) => g,
+                    _ => return Err(format!("{} is not a group or doesn't exist!", path)),
+                };
+                println!(
+                    "{}/ ({})",
+                    path::escape_name(&group.name),
+                    count_entries(group)
+                );
+                print_tree(group, "", level, !groups_only);
+                if summary {
+                    let groups = group
+                        .iter()
+                        .filter(|node| matches!(node, NodeRef::Group(_)))
+                        .count();
+                    println!(
+                        "\n{}, {}",
+                        plural(groups - 1, "group", "groups"),
+                        plural(count_entries(group), "entry", "entries")
+                    );
+                }
+                Ok(())
+            }
             Command::ChangeDir { path } => {
                 let db = self
                     .state
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                match db.change_current_group(&path) {
-                    false => Err(format!("{} is not a group or doesn't exist!", path)),
-                    true => {
+                let dir_stack = match path.as_str() {
+                    "-" => Some(db.previous_dir_stack.clone()),
+                    "" => Some(Vec::new()),
+                    path => path::resolve_group_stack(db, &self.state.last_results, path),
+                };
+                match dir_stack {
+                    Some(dir_stack) => {
+                        db.change_current_group(dir_stack);
                         editor_helper.set_dir_stack(db.dir_stack.clone());
                         Ok(())
                     }
+                    None => Err(format!("{} is not a group or doesn't exist!", path)),
                 }
             }
             Command::Show {
@@ -82,8 +142,7 @@ impl Executor {
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                let group = db.get_current_group();
-                if let Some(node) = db.get_node(group, &entry) {
+                if let Some(node) = path::resolve_node(db, &self.state.last_results, &entry) {
                     print_node(node, show_hidden, totp);
                     Ok(())
                 } else {
@@ -96,8 +155,7 @@ impl Executor {
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                let group = db.get_current_group();
-                match db.get_node(group, &entry) {
+                match path::resolve_node(db, &self.state.last_results, &entry) {
                     Some(NodeRef::Group(_)) | None => {
                         Err(format!("{} is not a group or doesn't exist!", entry))
                     }
@@ -113,8 +171,7 @@ impl Executor {
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                let group = db.get_current_group();
-                match db.get_node(group, &entry) {
+                match path::resolve_node(db, &self.state.last_results, &entry) {
                     Some(NodeRef::Group(_)) | None => {
                         Err(format!("{} is not a group or doesn't exist!", entry))
                     }
@@ -130,8 +187,7 @@ impl Executor {
                     .db
                     .as_mut()
                     .ok_or("Database not opened".to_string())?;
-                let group = db.get_current_group();
-                match db.get_node(group, &entry) {
+                match path::resolve_node(db, &self.state.last_results, &entry) {
                     Some(NodeRef::Group(_)) | None => {
                         Err(format!("{} is not a group or doesn't exist!", entry))
                     }
@@ -141,31 +197,398 @@ impl Executor {
                     }
                 }
             }
+            Command::NewEntry { path } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let group_uuid = match path::resolve_node(db, &self.state.last_results, &path) {
+                    Some(NodeRef::Group(g)) => g.uuid,
+                    Some(NodeRef::Entry(_)) | None => {
+                        return Err(format!("{} is not a group or doesn't exist!", path))
+                    }
+                };
+                let entry = read_new_entry()?;
+                db.find_group_mut(group_uuid).unwrap().add_child(entry);
+                db.dirty = true;
+                editor_helper.create_db_entries(&db.db);
+                println!("Entry created");
+                Ok(())
+            }
+            Command::EditEntry { entry } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let previous = match path::resolve_node(db, &self.state.last_results, &entry) {
+                    Some(NodeRef::Group(_)) | None => {
+                        return Err(format!("{} is not an entry or doesn't exist!", entry))
+                    }
+                    Some(NodeRef::Entry(e)) => e.clone(),
+                };
+                let mut edited = previous.clone();
+                edit_entry(&mut edited)?;
+                if !commit_entry_changes(&mut edited, previous) {
+                    println!("Entry not changed");
+                    return Ok(());
+                }
+                let uuid = edited.uuid;
+                *db.find_entry_mut(uuid).unwrap() = edited;
+                db.dirty = true;
+                editor_helper.create_db_entries(&db.db);
+                println!("Entry updated");
+                Ok(())
+            }
+            Command::EditEntryInEditor { entry } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let previous = match path::resolve_node(db, &self.state.last_results, &entry) {
+                    Some(NodeRef::Group(_)) | None => {
+                        return Err(format!("{} is not an entry or doesn't exist!", entry))
+                    }
+                    Some(NodeRef::Entry(e)) => e.clone(),
+                };
+                let mut edited = match entry_document::edit_entry_in_editor(&previous)? {
+                    Some(edited) => edited,
+                    None => {
+                        println!("Entry not changed");
+                        return Ok(());
+                    }
+                };
+                commit_entry_changes(&mut edited, previous);
+                let uuid = edited.uuid;
+                *db.find_entry_mut(uuid).unwrap() = edited;
+                db.dirty = true;
+                editor_helper.create_db_entries(&db.db);
+                println!("Entry updated");
+                Ok(())
+            }
+            Command::Remove {
+                recursive,
+                force,
+                path,
+            } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let uuid = match path::resolve_node(db, &self.state.last_results, &path) {
+                    None => return Err(format!("{} does not exist!", path)),
+                    Some(NodeRef::Group(g)) if g.uuid == db.db.root.uuid => {
+                        return Err("Can't remove root group!".to_string())
+                    }
+                    Some(NodeRef::Group(_)) if !recursive => {
+                        return Err(format!("{} is a group, use -r to remove it!", path))
+                    }
+                    Some(node) => node_uuid(&node),
+                };
+                // checked before recycle bin is created so that refused
+                // removal leaves database untouched
+                if let Some(recycle_bin) = db.recycle_bin().filter(|_| !force) {
+                    if !db.contains_node(recycle_bin, uuid) && db.contains_node(uuid, recycle_bin) {
+                        return Err(format!(
+                            "{} contains recycle bin, use -f to delete it permanently!",
+                            path
+                        ));
+                    }
+                }
+                let recycle_bin = if force {
+                    None
+                } else {
+                    db.get_or_create_recycle_bin()
+                };
+                match recycle_bin {
+                    Some(recycle_bin) if !db.contains_node(recycle_bin, uuid) => {
+                        db.move_node(uuid, recycle_bin);
+                        println!("{} moved to recycle bin", path);
+                    }
+                    _ => {
+                        db.delete_node(uuid);
+                        println!("{} permanently deleted", path);
+                    }
+                }
+                db.dirty = true;
+                db.fix_dir_stack();
+                editor_helper.set_dir_stack(db.dir_stack.clone());
+                editor_helper.create_db_entries(&db.db);
+                Ok(())
+            }
+            Command::Move {
+                source,
+                destination,
+            } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let uuid = match path::resolve_node(db, &self.state.last_results, &source) {
+                    None => return Err(format!("{} does not exist!", source)),
+                    Some(NodeRef::Group(g)) if g.uuid == db.db.root.uuid => {
+                        return Err("Can't move root group!".to_string())
+                    }
+                    Some(node) => node_uuid(&node),
+                };
+                let destination_node =
+                    path::resolve_node(db, &self.state.last_results, &destination);
+                let (group_uuid, new_name) = match destination_node {
+                    Some(NodeRef::Group(g)) => (g.uuid, None),
+                    Some(NodeRef::Entry(_)) => {
+                        return Err(format!("{} already exists!", destination))
+                    }
+                    None if destination.ends_with('/') => {
+                        return Err(format!("{} is not a group or doesn't exist!", destination))
+                    }
+                    None => {
+                        let (parent, name) = NodePath::parse(&destination).split_last();
+                        match (path::resolve(db, &parent), name) {
+                            (Some((_, NodeRef::Group(g))), Some(name)) => (g.uuid, Some(name)),
+                            _ => {
+                                return Err(format!(
+                                    "{} is not a group or doesn't exist!",
+                                    destination
+                                ))
+                            }
+                        }
+                    }
+                };
+                if db.contains_node(uuid, group_uuid) {
+                    return Err(format!("Can't move {} into itself!", source));
+                }
+
+                let parent_uuid = db.find_parent_group(uuid).map(|g| g.uuid);
+                if parent_uuid != Some(group_uuid) {
+                    db.move_node(uuid, group_uuid);
+                }
+                if let Some(new_name) = new_name {
+                    if let Some(group) = db.find_group_mut(uuid) {
+                        group.name = new_name;
+                        group.times.set_last_modification(Times::now());
+                    } else if let Some(entry) = db.find_entry_mut(uuid) {
+                        let previous = entry.clone();
+                        entry
+                            .fields
+                            .insert("Title".to_string(), Value::Unprotected(new_name));
+                        commit_entry_changes(entry, previous);
+                    }
+                }
+                db.dirty = true;
+                db.fix_dir_stack();
+                editor_helper.set_dir_stack(db.dir_stack.clone());
+                editor_helper.create_db_entries(&db.db);
+                Ok(())
+            }
+            Command::MakeDir { parents, path } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                db.create_group(&path, parents)?;
+                db.dirty = true;
+                editor_helper.create_db_entries(&db.db);
+                Ok(())
+            }
+            Command::RemoveDir { path } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                let uuid = match path::resolve_node(db, &self.state.last_results, &path) {
+                    Some(NodeRef::Group(g)) if g.uuid == db.db.root.uuid => {
+                        return Err("Can't remove root group!".to_string())
+                    }
+                    Some(NodeRef::Group(g)) if !g.children.is_empty() => {
+                        return Err(format!("{} is not empty!", path))
+                    }
+                    Some(NodeRef::Group(g)) => g.uuid,
+                    Some(NodeRef::Entry(_)) | None => {
+                        return Err(format!("{} is not a group or doesn't exist!", path))
+                    }
+                };
+                db.delete_node(uuid);
+                db.dirty = true;
+                db.fix_dir_stack();
+                editor_helper.set_dir_stack(db.dir_stack.clone());
+                editor_helper.create_db_entries(&db.db);
+                Ok(())
+            }
+            Command::Find { regex, term } => {
+                let db = self
+                    .state
+                    .db
+                    .as_ref()
+                    .ok_or("Database not opened".to_string())?;
+                let matches: Box<dyn Fn(&str) -> bool> = if regex {
+                    let regex = regex::Regex::new(&term)
+                        .map_err(|err| format!("Invalid regular expression: {}", err))?;
+                    Box::new(move |value| regex.is_match(value))
+                } else {
+                    let term = term.to_lowercase();
+                    Box::new(move |value| value.to_lowercase().contains(&term))
+                };
+                let found = db.find_entries(|e| {
+                    e.fields.values().any(|value| match value {
+                        Value::Unprotected(value) => matches(value),
+                        Value::Bytes(_) | Value::Protected(_) => false,
+                    })
+                });
+                if found.is_empty() {
+                    eprintln!("No matching entries");
+                }
+                for (i, (path, e)) in found.iter().enumerate() {
+                    println!("{:>3}. {}{}", i + 1, path, ambiguity_suffix(db, e.uuid));
+                }
+                self.state.last_results = found.iter().map(|(_, e)| e.uuid).collect();
+                Ok(())
+            }
+            Command::FuzzyFind { limit, query } => {
+                let db = self
+                    .state
+                    .db
+                    .as_ref()
+                    .ok_or("Database not opened".to_string())?;
+                let entries = db.find_entries(|_| true);
+                let titles: Vec<String> = entries
+                    .iter()
+                    .filter_map(|(_, e)| e.get_title().map(String::from))
+                    .collect();
+                let typos = fuzzy::typo_matches(&query, &titles);
+
+                // entries with typo in title are ranked after fuzzy matches
+                let mut ranked: Vec<(bool, i32, &str, Vec<usize>, Uuid)> = entries
+                    .iter()
+                    .filter_map(|(path, e)| {
+                        if let Some((score, indices)) = fuzzy::fuzzy_match(&query, path) {
+                            return Some((true, score, path.as_str(), indices, e.uuid));
+                        }
+                        let title = e.get_title()?.to_lowercase();
+                        typos.contains(&title).then_some((
+                            false,
+                            0,
+                            path.as_str(),
+                            Vec::new(),
+                            e.uuid,
+                        ))
+                    })
+                    .collect();
+                ranked.sort_by(|a, b| (b.0, b.1).cmp(&(a.0, a.1)).then(a.2.cmp(b.2)));
+                if ranked.is_empty() {
+                    eprintln!("No matching entries");
+                }
+                let highlight = std::io::stdout().is_terminal();
+                ranked.truncate(limit);
+                for (i, (_, _, path, indices, uuid)) in ranked.iter().enumerate() {
+                    println!(
+                        "{:>3}. {}{}",
+                        i + 1,
+                        highlight_chars(path, indices, highlight),
+                        ambiguity_suffix(db, *uuid)
+                    );
+                }
+                self.state.last_results = ranked.iter().map(|r| r.4).collect();
+                Ok(())
+            }
+            Command::Generate { options, copy } => {
+                let password = generator::generate_password(&options)?;
+                if copy {
+                    print_value_as_osc52(password.as_bytes());
+                } else {
+                    println!("{}", password);
+                }
+                // printed to stderr so that stdout contains only password
+                eprintln!("Entropy: {:.1} bits", generator::entropy_bits(&options));
+                Ok(())
+            }
             Command::ClearClipboard => {
                 print_value_as_osc52(&[]);
                 Ok(())
             }
-            Command::OpenDB { path, password } => {
+            Command::OpenDB {
+                path,
+                password,
+                keyfile,
+            } => {
                 if self.state.db.is_some() {
                     return Err("Database already opened!".to_string());
                 }
-                let mut file = File::open(&path).map_err(|err| format!("{}", err))?;
-                let db = Database::open(&mut file, DatabaseKey::new().with_password(&password))
-                    .map_err(|err| format!("{}", err))?;
-                editor_helper.create_db_entries(&db);
+                let password = match password {
+                    Some(password) => password,
+                    None => prompt::read_password("Enter password: ")?,
+                };
+                let key = key::build_key(Some(&password), keyfile.as_deref())?;
+                let db = Db::open(path, key)?;
+                editor_helper.create_db_entries(&db.db);
+                println!("{} successfully opened", db.path.display());
                 self.state = State::new(Some(db));
-                println!("{} successfully opened", path.display());
                 Ok(())
             }
-            Command::CloseDB => {
-                if self.state.db.is_none() {
-                    return Err("No database opened!".to_string());
+            Command::CreateDB { path, keyfile } => {
+                if self.state.db.is_some() {
+                    return Err("Database already opened!".to_string());
+                }
+                if path.exists() {
+                    return Err(format!("{} already exists!", path.display()));
+                }
+                let password = prompt::read_new_password("Enter password: ")?;
+                if password.is_empty() && keyfile.is_none() {
+                    return Err("Password must not be empty if key file is not used!".to_string());
+                }
+                let key = key::build_key(Some(&password), keyfile.as_deref())?;
+                let mut db = Db::create(path, key);
+                db.save(None)?;
+                editor_helper.create_db_entries(&db.db);
+                println!("{} successfully created", db.path.display());
+                self.state = State::new(Some(db));
+                Ok(())
+            }
+            Command::CloseDB { force } => {
+                match &self.state.db {
+                    None => return Err("No database opened!".to_string()),
+                    Some(db) if db.dirty && !force => {
+                        return Err(
+                            "Database has unsaved changes! Use 'save' or 'close -f' to discard them."
+                                .to_string(),
+                        );
+                    }
+                    Some(_) => {}
                 }
                 println!("Closing database!");
                 self.state = State::new(None);
                 editor_helper.clear_db();
                 Ok(())
             }
+            Command::History { .. } => {
+                // history belongs to interactive editor, handled by main loop
+                Err("History is available only in interactive session!".to_string())
+            }
+            Command::Save => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                db.save(None)?;
+                println!("{} successfully saved", db.path.display());
+                Ok(())
+            }
+            Command::SaveAs { path } => {
+                let db = self
+                    .state
+                    .db
+                    .as_mut()
+                    .ok_or("Database not opened".to_string())?;
+                db.save(Some(&path))?;
+                println!("{} successfully saved", db.path.display());
+                Ok(())
+            }
         }
     }
 }
@@ -203,28 +626,259 @@ fn copy_entry_field(entry: &Entry, field_name: &str) {
     }
 }
 
-fn list_node(node: NodeRef<'_>) {
-    match node {
-        NodeRef::Entry(e) => {
-            let title = e.get_title().unwrap_or("(no title");
-            println!("{}", title);
+fn read_new_entry() -> Result<Entry, String> {
+    let title = prompt::read_line("Title: ")?;
+    if title.is_empty() {
+        return Err("Title must not be empty!".to_string());
+    }
+    let username = prompt::read_line("UserName: ")?;
+    let password = prompt::read_new_secret("Password (!gen to generate): ")?;
+    warn_if_weak(&password);
+    let url = prompt::read_line("URL: ")?;
+    let notes = prompt::read_line("Notes: ")?;
+
+    let mut entry = Entry::new();
+    entry
+        .fields
+        .insert("Title".to_string(), Value::Unprotected(title));
+    entry
+        .fields
+        .insert("UserName".to_string(), Value::Unprotected(username));
+    entry.fields.insert(
+        "Password".to_string(),
+        Value::Protected(password.as_bytes().into()),
+    );
+    entry
+        .fields
+        .insert("URL".to_string(), Value::Unprotected(url));
+    entry
+        .fields
+        .insert("Notes".to_string(), Value::Unprotected(notes));
+    Ok(entry)
+}
+
+/// Print warning with reasons if password is easy to guess.
+fn warn_if_weak(password: &str) {
+    if password.is_empty() {
+        return;
+    }
+    let strength = strength::estimate(password);
+    if strength.is_weak() {
+        eprintln!("Warning: password is {}", strength);
+    }
+}
+
+const STANDARD_FIELDS: [&str; 5] = ["Title", "UserName", "Password", "URL", "Notes"];
+
+/// Prompt for new value of a single field.
+/// Returns `None` if field should keep its current value.
+fn edit_field_value(field_name: &str, value: &Value) -> Result<Option<Value>, String> {
+    match value {
+        Value::Bytes(_) => {
+            println!("{}: (bytes) can't be edited", field_name);
+            Ok(None)
         }
-        NodeRef::Group(g) => {
-            for node in &g.children {
-                match node.as_ref() {
-                    NodeRef::Group(g) => {
-                        println!("{}/", g.name);
-                    }
-                    NodeRef::Entry(e) => {
-                        let title = e.get_title().unwrap_or("(no title");
-                        println!("{}", title);
-                    }
-                }
+        Value::Unprotected(val) => {
+            let new_val = prompt::read_line_with_initial(&format!("{}: ", field_name), val)?;
+            Ok(Some(Value::Unprotected(new_val)))
+        }
+        Value::Protected(_) => {
+            let new_val = prompt::read_password(&format!(
+                "{} (empty to keep, !gen to generate): ",
+                field_name
+            ))?;
+            if new_val.is_empty() {
+                return Ok(None);
+            }
+            let new_val = prompt::confirm_or_generate(new_val)?;
+            if field_name == "Password" {
+                warn_if_weak(&new_val);
+            }
+            Ok(Some(Value::Protected(new_val.as_bytes().into())))
+        }
+    }
+}
+
+fn edit_entry(entry: &mut Entry) -> Result<(), String> {
+    for field_name in STANDARD_FIELDS {
+        let (value, missing) = match entry.fields.get(field_name) {
+            Some(value) => (value.clone(), false),
+            None if field_name == "Password" => (Value::Protected("".as_bytes().into()), true),
+            None => (Value::Unprotected(String::new()), true),
+        };
+        match edit_field_value(field_name, &value)? {
+            // missing field left empty is not a change
+            Some(Value::Unprotected(value)) if missing && value.is_empty() => {}
+            Some(value) => {
+                entry.fields.insert(field_name.to_string(), value);
+            }
+            None => {}
+        }
+    }
+    if entry.get_title().unwrap_or("").is_empty() {
+        return Err("Title must not be empty!".to_string());
+    }
+
+    let mut custom_fields: Vec<String> = entry
+        .fields
+        .keys()
+        .filter(|name| !STANDARD_FIELDS.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    custom_fields.sort();
+    for field_name in custom_fields {
+        if let Some(value) = edit_field_value(&field_name, &entry.fields[&field_name])? {
+            entry.fields.insert(field_name, value);
+        }
+    }
+
+    loop {
+        let name = prompt::read_line("Add field (name, -name to remove, empty to finish): ")?;
+        if name.is_empty() {
+            break;
+        }
+        if let Some(name) = name.strip_prefix('-') {
+            if STANDARD_FIELDS.contains(&name) {
+                eprintln!("Can't remove standard field {}!", name);
+            } else if entry.fields.remove(name).is_none() {
+                eprintln!("{} does not exist!", name);
+            }
+            continue;
+        }
+        if entry.fields.contains_key(&name) || STANDARD_FIELDS.contains(&name.as_str()) {
+            eprintln!("{} already exists!", name);
+            continue;
+        }
+        let protect = prompt::read_line("Protect value? [y/N]: ")?;
+        let value = if protect.eq_ignore_ascii_case("y") {
+            let val = prompt::read_new_secret(&format!("{} (!gen to generate): ", name))?;
+            Value::Protected(val.as_bytes().into())
+        } else {
+            Value::Unprotected(prompt::read_line(&format!("{}: ", name))?)
+        };
+        entry.fields.insert(name, value);
+    }
+    Ok(())
+}
+
+/// Record previous version of entry in its history and
+/// update modification time if entry was changed.
+fn commit_entry_changes(entry: &mut Entry, previous: Entry) -> bool {
+    if *entry == previous {
+        return false;
+    }
+    entry
+        .history
+        .get_or_insert_with(History::default)
+        .add_entry(previous);
+    entry.times.set_last_modification(Times::now());
+    true
+}
+
+/// Mark characters at given indices as bold green if `enabled`.
+fn highlight_chars(text: &str, indices: &[usize], enabled: bool) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if indices.contains(&i) {
+                format!("\x1b[1;32m{}\x1b[0m", c)
+            } else {
+                c.to_string()
             }
+        })
+        .collect()
+}
+
+/// Print children of group below its line, indented by `prefix`,
+/// descending at most `level` levels.
+fn print_tree(group: &Group, prefix: &str, level: Option<u32>, entries: bool) {
+    if level == Some(0) {
+        return;
+    }
+    let duplicates = path::duplicate_names(group);
+    let nodes: Vec<NodeRef<'_>> = group
+        .children
+        .iter()
+        .map(|n| n.as_ref())
+        .filter(|node| entries || matches!(node, NodeRef::Group(_)))
+        .collect();
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i == nodes.len() - 1;
+        let name = state::node_name(node);
+        let mut line = path::escape_name(name.unwrap_or("(no title)"));
+        if let NodeRef::Group(g) = node {
+            line = format!("{}/ ({})", line, count_entries(g));
+        }
+        if name.is_some_and(|name| duplicates.contains(name)) {
+            line = format!("{}  {}", line, path::uuid_reference(node_uuid(node)));
+        }
+        println!("{}{}{}", prefix, if last { "└── " } else { "├── " }, line);
+        if let NodeRef::Group(g) = node {
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            print_tree(g, &prefix, level.map(|level| level - 1), entries);
         }
     }
 }
 
+/// Number of entries in group and all its subgroups.
+fn count_entries(group: &Group) -> usize {
+    group
+        .iter()
+        .filter(|node| matches!(node, NodeRef::Entry(_)))
+        .count()
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// UUID reference printed after path of node if the path could lead to
+/// another node, i.e. node or one of its groups shares name with a sibling.
+fn ambiguity_suffix(db: &Db, uuid: Uuid) -> String {
+    let mut current = uuid;
+    while let Some(parent) = db.find_parent_group(current) {
+        let name = parent
+            .children
+            .iter()
+            .map(|node| node.as_ref())
+            .find(|node| node_uuid(node) == current)
+            .and_then(|node| state::node_name(&node));
+        if name.is_some_and(|name| path::duplicate_names(parent).contains(name)) {
+            return format!("  {}", path::uuid_reference(uuid));
+        }
+        current = parent.uuid;
+    }
+    String::new()
+}
+
+/// Print numbered children of group (or entry itself),
+/// returns their UUIDs so that they can be referred by number.
+fn list_node(node: NodeRef<'_>) -> Vec<Uuid> {
+    let (nodes, duplicates): (Vec<NodeRef<'_>>, _) = match node {
+        NodeRef::Entry(_) => (vec![node], Default::default()),
+        NodeRef::Group(g) => (
+            g.children.iter().map(|n| n.as_ref()).collect(),
+            path::duplicate_names(g),
+        ),
+    };
+    for (i, node) in nodes.iter().enumerate() {
+        let name = state::node_name(node);
+        let mut line = path::escape_name(name.unwrap_or("(no title)"));
+        if let NodeRef::Group(_) = node {
+            line.push('/');
+        }
+        // nodes with the same name can only be told apart by UUID
+        if name.is_some_and(|name| duplicates.contains(name)) {
+            line = format!("{}  {}", line, path::uuid_reference(node_uuid(node)));
+        }
+        println!("{:>3}. {}", i + 1, line);
+    }
+    nodes.iter().map(node_uuid).collect()
+}
+
 fn get_totp(e: &Entry) -> Result<String, String> {
     e.get("otp")
         .ok_or("Entry does not have totp!".to_string())
@@ -286,6 +940,10 @@ fn print_node(node: NodeRef<'_>, show_hidden: bool, totp: bool) {
             println!("{:>FIELD_NAME_WIDTH$}: {}", "Title", title);
             println!("{:>FIELD_NAME_WIDTH$}: {}", "UserName", username);
             println!("{:>FIELD_NAME_WIDTH$}: {}", "Password", password);
+            if show_hidden && e.fields.contains_key("Password") {
+                let strength = strength::estimate(password);
+                println!("{:>FIELD_NAME_WIDTH$}: {}", "Strength", strength);
+            }
 
             for (field_name, field_value) in &e.fields {
                 if field_name == "otp" && show_hidden {
@@ -0,0 +1,274 @@
+//! Resolution of paths to groups and entries shared by all commands.
+//!
+//! Paths are relative to current group unless they start with `/` (or `~`)
+//! which is the root group. `.` is the group itself and `..` its parent,
+//! parent of root is root. Nodes listed by last `ls`, `find` or `ff` can be
+//! referred as `#N`.
+//!
+//! Segment `@<uuid-prefix>` refers to child with UUID starting with given
+//! hex digits, which is the only way to reach nodes with duplicate names.
+//! If it's the first segment and no child matches, whole database is searched.
+//!
+//! Slash in group or entry name is written as `\/` and backslash as `\\`,
+//! other backslashes are taken literally.
+
+use std::collections::HashSet;
+
+use keepass::db::{Group, NodeRef};
+use uuid::Uuid;
+
+use crate::executor::state::{node_name, node_uuid, Db};
+
+/// Path split into segments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePath {
+    pub absolute: bool,
+    pub segments: Vec<String>,
+}
+
+impl NodePath {
+    pub fn parse(path: &str) -> Self {
+        let (absolute, path) = if let Some(path) = path.strip_prefix('/') {
+            (true, path)
+        } else if path == "~" {
+            (true, "")
+        } else if let Some(path) = path.strip_prefix("~/") {
+            (true, path)
+        } else {
+            (false, path)
+        };
+        let segments = split_escaped(path)
+            .into_iter()
+            .map(unescape_name)
+            .filter(|segment| !segment.is_empty() && segment != ".")
+            .collect();
+        Self { absolute, segments }
+    }
+
+    /// Split path into path of parent and name of last segment.
+    /// Name is `None` if path has no segments or ends with `..`.
+    pub fn split_last(mut self) -> (Self, Option<String>) {
+        match self.segments.last() {
+            Some(last) if last != ".." => {
+                let name = self.segments.pop();
+                (self, name)
+            }
+            _ => (self, None),
+        }
+    }
+}
+
+/// Split path at slashes which are not escaped, segments are left escaped.
+pub fn split_escaped(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' => {
+                segments.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&path[start..]);
+    segments
+}
+
+pub fn unescape_name(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('/' | '\\')) => unescaped.push(c),
+                Some(c) => unescaped.extend(['\\', c]),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Escape name so that it's read as single path segment.
+pub fn escape_name(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('/', "\\/")
+}
+
+/// Short form of UUID used to tell apart nodes with the same name.
+pub fn uuid_reference(uuid: Uuid) -> String {
+    format!("@{}", &uuid.simple().to_string()[..8])
+}
+
+/// Names shared by more than one child of group.
+pub fn duplicate_names(group: &Group) -> HashSet<&str> {
+    let mut seen = HashSet::new();
+    group
+        .children
+        .iter()
+        .filter_map(|node| node_name(&node.as_ref()))
+        .filter(|name| !seen.insert(*name))
+        .collect()
+}
+
+/// The only node with UUID starting with given hex digits.
+fn find_by_uuid_prefix<'a>(
+    mut nodes: impl Iterator<Item = NodeRef<'a>>,
+    prefix: &str,
+) -> Option<NodeRef<'a>> {
+    let prefix = prefix.to_lowercase();
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut matching = nodes
+        .by_ref()
+        .filter(|node| node_uuid(node).simple().to_string().starts_with(&prefix));
+    match (matching.next(), matching.next()) {
+        (Some(node), None) => Some(node),
+        _ => None,
+    }
+}
+
+fn child<'a>(group: &'a Group, name: &str) -> Option<NodeRef<'a>> {
+    group.get(&[name]).or_else(|| {
+        let prefix = name.strip_prefix('@')?;
+        find_by_uuid_prefix(group.children.iter().map(|node| node.as_ref()), prefix)
+    })
+}
+
+/// Node anywhere in database referred by `@<uuid-prefix>`,
+/// together with its stack as returned by `resolve`.
+fn find_anywhere<'a>(db: &'a Db, segment: &str) -> Option<(Vec<Uuid>, NodeRef<'a>)> {
+    let prefix = segment.strip_prefix('@')?;
+    let node = find_by_uuid_prefix(db.db.root.iter(), prefix)?;
+    let stack = match node {
+        NodeRef::Group(g) => db.group_path(g.uuid)?,
+        NodeRef::Entry(e) => db.group_path(db.find_parent_group(e.uuid)?.uuid)?,
+    };
+    Some((stack, node))
+}
+
+/// Resolve path to node together with stack of groups leading to it,
+/// in the same form as `dir_stack` (node itself included if it's a group).
+pub fn resolve<'a>(db: &'a Db, path: &NodePath) -> Option<(Vec<Uuid>, NodeRef<'a>)> {
+    let mut stack = if path.absolute {
+        Vec::new()
+    } else {
+        db.dir_stack.clone()
+    };
+    let group_at = |stack: &[Uuid]| match stack.last() {
+        Some(uuid) => db.find_group(*uuid),
+        None => Some(&db.db.root),
+    };
+    for (i, segment) in path.segments.iter().enumerate() {
+        if segment == ".." {
+            stack.pop();
+            continue;
+        }
+        let node = match child(group_at(&stack)?, segment) {
+            Some(node) => node,
+            None if i == 0 && !path.absolute => {
+                let (found_stack, node) = find_anywhere(db, segment)?;
+                stack = found_stack;
+                if let NodeRef::Entry(_) = node {
+                    if i != path.segments.len() - 1 {
+                        return None;
+                    }
+                    return Some((stack, node));
+                }
+                continue;
+            }
+            None => return None,
+        };
+        match node {
+            NodeRef::Group(g) => stack.push(g.uuid),
+            NodeRef::Entry(e) if i == path.segments.len() - 1 => {
+                return Some((stack, NodeRef::Entry(e)));
+            }
+            NodeRef::Entry(_) => return None,
+        }
+    }
+    Some((stack.clone(), NodeRef::Group(group_at(&stack)?)))
+}
+
+/// Node referred by `#N` (or just `N` if there is no node with such name)
+/// as N-th result of last `ls`, `find` or `ff`.
+pub fn resolve_reference<'a>(db: &'a Db, results: &[Uuid], path: &str) -> Option<NodeRef<'a>> {
+    let index = match path.strip_prefix('#') {
+        Some(index) => index,
+        None if resolve(db, &NodePath::parse(path)).is_none() => path,
+        None => return None,
+    };
+    let index: usize = index.parse().ok()?;
+    let uuid = results.get(index.checked_sub(1)?)?;
+    db.find_node(*uuid)
+}
+
+/// Resolve path or reference to previous result.
+pub fn resolve_node<'a>(db: &'a Db, results: &[Uuid], path: &str) -> Option<NodeRef<'a>> {
+    resolve_reference(db, results, path).or_else(|| db.get_node(path))
+}
+
+/// Stack of groups leading to group with given path or reference.
+pub fn resolve_group_stack(db: &Db, results: &[Uuid], path: &str) -> Option<Vec<Uuid>> {
+    match resolve_reference(db, results, path) {
+        Some(NodeRef::Group(g)) => db.group_path(g.uuid),
+        Some(NodeRef::Entry(_)) => None,
+        None => match resolve(db, &NodePath::parse(path))? {
+            (stack, NodeRef::Group(_)) => Some(stack),
+            (_, NodeRef::Entry(_)) => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::command::split_words;
+
+    fn segments(path: &str) -> Vec<String> {
+        NodePath::parse(path).segments
+    }
+
+    #[test]
+    fn parse_absolute_and_relative() {
+        for path in ["/", "~", "~/"] {
+            let path = NodePath::parse(path);
+            assert!(path.absolute);
+            assert!(path.segments.is_empty());
+        }
+        assert!(NodePath::parse("~/a").absolute);
+        assert!(!NodePath::parse("a/b").absolute);
+        assert_eq!(segments("a/./../b//"), ["a", "..", "b"]);
+    }
+
+    #[test]
+    fn escaped_names() {
+        assert_eq!(segments(r"a\/b/c"), ["a/b", "c"]);
+        assert_eq!(segments(r"a\\/b"), [r"a\", "b"]);
+        assert_eq!(segments(r"a\b"), [r"a\b"]);
+        assert_eq!(segments(r"a\"), [r"a\"]);
+    }
+
+    #[test]
+    fn split_last() {
+        let (parent, name) = NodePath::parse("/a/b").split_last();
+        assert_eq!(parent.segments, ["a"]);
+        assert_eq!(name.as_deref(), Some("b"));
+        assert_eq!(NodePath::parse("a/..").split_last().1, None);
+    }
+
+    #[test]
+    fn escape_round_trip() {
+        for name in ["a/b", r"a\b", r"a\", r"a\/b", "x y", "#1"] {
+            assert_eq!(segments(&escape_name(name)), [name]);
+            // completer inserts names quoted
+            let words = split_words(&format!("ls {}", shlex::quote(&escape_name(name))));
+            assert_eq!(segments(&words[1]), [name]);
+        }
+    }
+}
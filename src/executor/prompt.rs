@@ -0,0 +1,63 @@
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use crate::executor::{generator, PasswordInput};
+
+fn map_readline_err(err: ReadlineError) -> String {
+    match err {
+        ReadlineError::Interrupted | ReadlineError::Eof => "Aborted!".to_string(),
+        err => format!("Error reading line: {}", err),
+    }
+}
+
+/// Read single line of plain text input.
+pub fn read_line(prompt: &str) -> Result<String, String> {
+    let mut rl = Editor::<()>::new().map_err(map_readline_err)?;
+    rl.readline(prompt).map_err(map_readline_err)
+}
+
+/// Read single line of plain text input with editable initial value.
+pub fn read_line_with_initial(prompt: &str, initial: &str) -> Result<String, String> {
+    let mut rl = Editor::<()>::new().map_err(map_readline_err)?;
+    rl.readline_with_initial(prompt, (initial, ""))
+        .map_err(map_readline_err)
+}
+
+/// Read single line of masked input.
+pub fn read_password(prompt: &str) -> Result<String, String> {
+    let mut rl = Editor::new().map_err(map_readline_err)?;
+    rl.set_helper(Some(PasswordInput));
+    rl.readline(prompt).map_err(map_readline_err)
+}
+
+/// Read masked input twice and make sure both match.
+pub fn read_new_password(prompt: &str) -> Result<String, String> {
+    confirm_password(read_password(prompt)?)
+}
+
+/// Ask for already entered password once more and make sure both match.
+pub fn confirm_password(password: String) -> Result<String, String> {
+    let retyped = read_password("Retype password: ")?;
+    if password != retyped {
+        return Err("Passwords do not match!".to_string());
+    }
+    Ok(password)
+}
+
+/// Confirm entered secret value, or generate random password
+/// if user typed `!gen [options]` instead.
+pub fn confirm_or_generate(password: String) -> Result<String, String> {
+    match generator::generate_from_prompt_input(&password) {
+        Some(generated) => {
+            let generated = generated?;
+            println!("Generated password of length {}", generated.chars().count());
+            Ok(generated)
+        }
+        None => confirm_password(password),
+    }
+}
+
+/// Read new secret value of entry field, see `confirm_or_generate`.
+pub fn read_new_secret(prompt: &str) -> Result<String, String> {
+    confirm_or_generate(read_password(prompt)?)
+}
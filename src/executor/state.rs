@@ -1,35 +1,122 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
 use keepass::{
-    db::{Group, Node, NodeRef},
-    Database,
+    config::{DatabaseConfig, DatabaseVersion},
+    db::{DeletedObject, Entry, Group, Node, NodeRef, Times},
+    error::{DatabaseKeyError, DatabaseOpenError},
+    Database, DatabaseKey,
 };
 use uuid::Uuid;
 
+use crate::executor::path::{self, NodePath};
+
 pub struct State {
     pub db: Option<Db>,
+    // Nodes printed by last `ls`, `find` or `ff`, referred as `#N`
+    pub last_results: Vec<Uuid>,
 }
 
 pub struct Db {
     pub db: Database,
+    // File the database was opened from, used by `save`
+    pub path: PathBuf,
+    // Composite key used to open the database, reused when saving
+    pub key: DatabaseKey,
+    // Set whenever in-memory database differs from the one on disk
+    pub dirty: bool,
     // UUIDs of directory/group stack
     pub dir_stack: Vec<Uuid>,
+    // Directory stack before last `cd`, used by `cd -`
+    pub previous_dir_stack: Vec<Uuid>,
 }
 
 impl State {
-    pub fn new(db: Option<Database>) -> Self {
+    pub fn new(db: Option<Db>) -> Self {
         Self {
-            db: db.map(Db::new),
+            db,
+            last_results: Vec::new(),
         }
     }
 }
 
 impl Db {
-    fn new(db: Database) -> Self {
+    pub fn new(db: Database, path: PathBuf, key: DatabaseKey) -> Self {
         Self {
             db,
+            path,
+            key,
+            dirty: false,
             dir_stack: Vec::new(),
+            previous_dir_stack: Vec::new(),
         }
     }
 
+    /// Open database from given file with given composite key.
+    pub fn open(path: PathBuf, key: DatabaseKey) -> Result<Self, String> {
+        let mut file = File::open(&path).map_err(|err| format!("{}", err))?;
+        let db = Database::open(&mut file, key.clone()).map_err(|err| match err {
+            DatabaseOpenError::Key(DatabaseKeyError::IncorrectKey) => {
+                "Incorrect password or key file!".to_string()
+            }
+            err => format!("{}", err),
+        })?;
+        Ok(Self::new(db, path, key))
+    }
+
+    /// Create empty KDBX4 database with recycle bin.
+    /// Root group is named after database file.
+    pub fn create(path: PathBuf, key: DatabaseKey) -> Self {
+        let mut database = Database::new(DatabaseConfig::default());
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or("Root".to_string());
+        database.root.name = name.clone();
+        database.meta.database_name = Some(name);
+        database.meta.generator = Some("kpcli".to_string());
+        let mut db = Self::new(database, path, key);
+        db.get_or_create_recycle_bin();
+        db
+    }
+
+    /// Serialize database as KDBX4 to given path (or the one it was opened from).
+    /// Data is first written to temporary file which is then renamed over the
+    /// destination so that failed save never leaves truncated database behind.
+    /// Symlinks are followed and permissions of existing file are kept
+    /// (new files are readable only by owner).
+    pub fn save(&mut self, path: Option<&Path>) -> Result<(), String> {
+        if !matches!(self.db.config.version, DatabaseVersion::KDB4(_)) {
+            println!("Database will be upgraded to KDBX4 format");
+            self.db.config.version = DatabaseVersion::KDB4(0);
+        }
+        let path = path.unwrap_or(&self.path).to_path_buf();
+        // rename would replace symlink itself instead of file it points to
+        let target = path.canonicalize().unwrap_or_else(|_| path.clone());
+        let mut tmp_path = target.clone().into_os_string();
+        tmp_path.push(".kpcli-tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        let _ = std::fs::remove_file(&tmp_path);
+
+        let result = create_private_file(&tmp_path, &target)
+            .map_err(|err| format!("{}", err))
+            .and_then(|mut file| {
+                self.db
+                    .save(&mut file, self.key.clone())
+                    .map_err(|err| format!("{}", err))?;
+                file.sync_all().map_err(|err| format!("{}", err))
+            })
+            .and_then(|_| std::fs::rename(&tmp_path, &target).map_err(|err| format!("{}", err)));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result?;
+
+        self.path = path;
+        self.dirty = false;
+        Ok(())
+    }
+
     pub fn find_group(&self, uuid: Uuid) -> Option<&Group> {
         self.db.root.iter().find_map(|n| match n {
             NodeRef::Group(g) if g.uuid == uuid => Some(g),
@@ -37,51 +124,324 @@ impl Db {
         })
     }
 
-    pub fn get_current_group(&self) -> &Group {
-        match self.dir_stack.last() {
-            None => &self.db.root,
-            Some(value) => self.find_group(*value).unwrap(),
-        }
+    pub fn find_node(&self, uuid: Uuid) -> Option<NodeRef<'_>> {
+        self.db.root.iter().find(|n| node_uuid(n) == uuid)
+    }
+
+    pub fn find_group_mut(&mut self, uuid: Uuid) -> Option<&mut Group> {
+        find_group_mut(&mut self.db.root, uuid)
+    }
+
+    pub fn find_entry_mut(&mut self, uuid: Uuid) -> Option<&mut Entry> {
+        find_entry_mut(&mut self.db.root, uuid)
     }
 
-    pub fn change_current_group(&mut self, path: &str) -> bool {
-        let previous_stack = self.dir_stack.clone();
-        for path in path.split('/') {
-            if path == ".." {
-                self.dir_stack.pop();
+    /// Whether group with given UUID is or contains node with `uuid`.
+    pub fn contains_node(&self, group_uuid: Uuid, uuid: Uuid) -> bool {
+        self.find_group(group_uuid)
+            .is_some_and(|g| g.iter().any(|n| node_uuid(&n) == uuid))
+    }
+
+    /// Find group which directly contains node with given UUID.
+    pub fn find_parent_group(&self, uuid: Uuid) -> Option<&Group> {
+        self.db.root.iter().find_map(|n| match n {
+            NodeRef::Group(g) if g.children.iter().any(|c| node_uuid(&c.as_ref()) == uuid) => {
+                Some(g)
+            }
+            _ => None,
+        })
+    }
+
+    /// UUIDs of groups leading from root to group with given UUID,
+    /// in the same form as `dir_stack`.
+    pub fn group_path(&self, uuid: Uuid) -> Option<Vec<Uuid>> {
+        group_path(&self.db.root, uuid)
+    }
+
+    /// Full paths of entries for which `matches` returns true.
+    /// Groups with searching disabled (like recycle bin) are skipped.
+    pub fn find_entries(&self, matches: impl Fn(&Entry) -> bool) -> Vec<(String, &Entry)> {
+        let mut found = Vec::new();
+        find_entries(&self.db.root, "", &matches, &mut found);
+        found
+    }
+
+    /// Create group with given path.
+    /// Missing intermediate groups are created only if `parents` is set,
+    /// in which case existing group is not an error either.
+    pub fn create_group(&mut self, path: &str, parents: bool) -> Result<(), String> {
+        let path = NodePath::parse(path);
+        if path.segments.last().is_none_or(|name| name == "..") {
+            return Err("Group name must not be empty!".to_string());
+        }
+        let names = &path.segments;
+        let mut stack = if path.absolute {
+            Vec::new()
+        } else {
+            self.dir_stack.clone()
+        };
+        for (i, name) in names.iter().enumerate() {
+            if name == ".." {
+                stack.pop();
                 continue;
             }
-            match self.get_node(self.get_current_group(), path) {
-                Some(NodeRef::Group(g)) => {
-                    self.dir_stack.push(g.uuid);
+            let is_last = i == names.len() - 1;
+            let parent_uuid = stack.last().copied().unwrap_or(self.db.root.uuid);
+            let parent = self.find_group(parent_uuid).unwrap();
+            match parent.get(&[name]) {
+                Some(NodeRef::Group(g)) if !is_last || parents => {
+                    stack.push(g.uuid);
+                }
+                Some(NodeRef::Entry(_)) if !is_last => {
+                    return Err(format!("{} is not a group!", names[..=i].join("/")));
+                }
+                Some(_) => {
+                    return Err(format!("{} already exists!", names[..=i].join("/")));
                 }
-                Some(NodeRef::Entry(_)) | None => {
-                    self.dir_stack = previous_stack;
-                    return false;
+                None if is_last || parents => {
+                    let group = Group::new(name);
+                    stack.push(group.uuid);
+                    self.find_group_mut(parent_uuid).unwrap().add_child(group);
+                }
+                None => {
+                    return Err(format!(
+                        "{} doesn't exist, use -p to create it!",
+                        names[..=i].join("/")
+                    ));
                 }
             }
         }
+        Ok(())
+    }
+
+    /// UUID of recycle bin group if it exists and is enabled.
+    pub fn recycle_bin(&self) -> Option<Uuid> {
+        if self.db.meta.recyclebin_enabled == Some(false) {
+            return None;
+        }
+        self.db
+            .meta
+            .recyclebin_uuid
+            .filter(|uuid| self.find_group(*uuid).is_some())
+    }
+
+    /// Return UUID of recycle bin group, creating it if it doesn't exist.
+    /// Returns `None` if recycle bin is disabled in database settings.
+    pub fn get_or_create_recycle_bin(&mut self) -> Option<Uuid> {
+        if self.db.meta.recyclebin_enabled == Some(false) {
+            return None;
+        }
+        if let Some(uuid) = self.recycle_bin() {
+            return Some(uuid);
+        }
+        let mut recycle_bin = Group::new("Recycle Bin");
+        // trash can icon in KeePass
+        recycle_bin.icon_id = Some(43);
+        recycle_bin.enable_autotype = Some("false".to_string());
+        recycle_bin.enable_searching = Some("false".to_string());
+        let uuid = recycle_bin.uuid;
+        self.db.root.add_child(recycle_bin);
+        self.db.meta.recyclebin_enabled = Some(true);
+        self.db.meta.recyclebin_uuid = Some(uuid);
+        self.db.meta.recyclebin_changed = Some(Times::now());
+        Some(uuid)
+    }
+
+    /// Move node with given UUID under group `group_uuid`.
+    /// Caller must make sure that group is not the node itself or its descendant.
+    pub fn move_node(&mut self, uuid: Uuid, group_uuid: Uuid) -> bool {
+        if self.find_group(group_uuid).is_none() {
+            return false;
+        }
+        let mut node = match remove_node(&mut self.db.root, uuid) {
+            Some(node) => node,
+            None => return false,
+        };
+        match &mut node {
+            Node::Group(g) => g.times.set_location_changed(Times::now()),
+            Node::Entry(e) => e.times.set_location_changed(Times::now()),
+        }
+        self.find_group_mut(group_uuid).unwrap().add_child(node);
+        true
+    }
+
+    /// Permanently delete node with given UUID, recording it
+    /// and all of its descendants as deleted objects.
+    pub fn delete_node(&mut self, uuid: Uuid) -> bool {
+        let node = match remove_node(&mut self.db.root, uuid) {
+            Some(node) => node,
+            None => return false,
+        };
+        let deletion_time = Times::now();
+        let deleted = node.as_ref();
+        let deleted: Vec<Uuid> = match deleted {
+            NodeRef::Group(g) => g.iter().map(|n| node_uuid(&n)).collect(),
+            NodeRef::Entry(e) => vec![e.uuid],
+        };
+        self.db
+            .deleted_objects
+            .objects
+            .extend(deleted.into_iter().map(|uuid| DeletedObject {
+                uuid,
+                deletion_time,
+            }));
         true
     }
 
-    pub fn get_node<'a>(&'a self, group: &'a Group, path: &str) -> Option<NodeRef<'a>> {
-        match path {
-            "" | "./" | "." => Some(NodeRef::Group(group)),
-            _ => {
-                let full_path = path.split('/').collect::<Vec<_>>();
-                group.get(&full_path)
+    /// Stack leading to the deepest group of `stack` which still exists,
+    /// following it wherever it was moved.
+    fn rebuild_stack(&self, stack: &[Uuid]) -> Vec<Uuid> {
+        stack
+            .iter()
+            .rev()
+            .find_map(|uuid| self.group_path(*uuid))
+            .unwrap_or_default()
+    }
+
+    /// Rebuild directory stacks after groups were moved or removed.
+    pub fn fix_dir_stack(&mut self) {
+        self.dir_stack = self.rebuild_stack(&self.dir_stack);
+        self.previous_dir_stack = self.rebuild_stack(&self.previous_dir_stack);
+    }
+
+    pub fn get_current_group(&self) -> &Group {
+        match self.dir_stack.last() {
+            None => &self.db.root,
+            Some(value) => self.find_group(*value).unwrap(),
+        }
+    }
+
+    /// Change current group, previous one is remembered for `cd -`.
+    pub fn change_current_group(&mut self, dir_stack: Vec<Uuid>) {
+        self.previous_dir_stack = std::mem::replace(&mut self.dir_stack, dir_stack);
+    }
+
+    /// Node with given path, see `path` module.
+    pub fn get_node(&self, path: &str) -> Option<NodeRef<'_>> {
+        path::resolve(self, &NodePath::parse(path)).map(|(_, node)| node)
+    }
+}
+
+fn find_group_mut(group: &mut Group, uuid: Uuid) -> Option<&mut Group> {
+    if group.uuid == uuid {
+        return Some(group);
+    }
+    group.children.iter_mut().find_map(|n| match n {
+        Node::Group(g) => find_group_mut(g, uuid),
+        Node::Entry(_) => None,
+    })
+}
+
+fn group_path(group: &Group, uuid: Uuid) -> Option<Vec<Uuid>> {
+    if group.uuid == uuid {
+        return Some(Vec::new());
+    }
+    group.children.iter().find_map(|n| match n {
+        Node::Group(g) => group_path(g, uuid).map(|mut path| {
+            path.insert(0, g.uuid);
+            path
+        }),
+        Node::Entry(_) => None,
+    })
+}
+
+fn remove_node(group: &mut Group, uuid: Uuid) -> Option<Node> {
+    let index = group.children.iter().position(|n| match n {
+        Node::Group(g) => g.uuid == uuid,
+        Node::Entry(e) => e.uuid == uuid,
+    });
+    if let Some(index) = index {
+        return Some(group.children.remove(index));
+    }
+    group.children.iter_mut().find_map(|n| match n {
+        Node::Group(g) => remove_node(g, uuid),
+        Node::Entry(_) => None,
+    })
+}
+
+fn find_entries<'a>(
+    group: &'a Group,
+    path: &str,
+    matches: &impl Fn(&Entry) -> bool,
+    found: &mut Vec<(String, &'a Entry)>,
+) {
+    for node in &group.children {
+        match node {
+            Node::Group(g) if g.enable_searching.as_deref() != Some("false") => {
+                let path = format!("{}/{}", path, path::escape_name(&g.name));
+                find_entries(g, &path, matches, found);
             }
+            Node::Group(_) => {}
+            Node::Entry(e) if matches(e) => {
+                let title = e.get_title().unwrap_or("(no title)");
+                found.push((format!("{}/{}", path, path::escape_name(title)), e));
+            }
+            Node::Entry(_) => {}
         }
     }
 }
 
+pub fn node_uuid(node: &NodeRef<'_>) -> Uuid {
+    match node {
+        NodeRef::Group(g) => g.uuid,
+        NodeRef::Entry(e) => e.uuid,
+    }
+}
+
+/// Name of group or title of entry.
+pub fn node_name<'a>(node: &NodeRef<'a>) -> Option<&'a str> {
+    match node {
+        NodeRef::Group(g) => Some(&g.name),
+        NodeRef::Entry(e) => e.get_title(),
+    }
+}
+
+fn find_entry_mut(group: &mut Group, uuid: Uuid) -> Option<&mut Entry> {
+    group.children.iter_mut().find_map(|n| match n {
+        Node::Group(g) => find_entry_mut(g, uuid),
+        Node::Entry(e) if e.uuid == uuid => Some(e),
+        Node::Entry(_) => None,
+    })
+}
+
+/// Names of all children of group, nodes with duplicate
+/// names are also included as `@<uuid-prefix>`.
 pub fn get_all_prefixes_under_group(group: &Group) -> Vec<String> {
+    let duplicates = path::duplicate_names(group);
     group
         .children
         .iter()
-        .filter_map(|node| match node {
-            Node::Group(g) => g.name.to_string().into(),
-            Node::Entry(e) => e.get_title().map(String::from),
+        .flat_map(|node| {
+            let node = node.as_ref();
+            let name = node_name(&node);
+            let reference = name
+                .filter(|name| duplicates.contains(name))
+                .map(|_| path::uuid_reference(node_uuid(&node)));
+            name.map(String::from).into_iter().chain(reference)
         })
         .collect()
 }
+
+/// Create file with the same permissions as `original`,
+/// or readable only by owner if `original` doesn't exist.
+fn create_private_file(path: &Path, original: &Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mode = std::fs::metadata(original)
+            .map(|metadata| metadata.permissions().mode() & 0o7777)
+            .unwrap_or(0o600);
+        options.mode(mode);
+        let file = options.open(path)?;
+        // mode given to open is reduced by umask
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = original;
+        options.open(path)
+    }
+}
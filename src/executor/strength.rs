@@ -0,0 +1,652 @@
+//! Password strength estimation in the spirit of zxcvbn.
+//!
+//! Password is split into patterns (dictionary words, keyboard walks,
+//! sequences, repeats, dates) and brute-forced segments, the split requiring
+//! the least guesses is used as an estimate of how hard it is to crack.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use chrono::Datelike;
+
+use crate::executor::generator;
+
+// Most common passwords, ordered by popularity
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456",
+    "password",
+    "123456789",
+    "12345678",
+    "12345",
+    "qwerty",
+    "1234567",
+    "111111",
+    "1234567890",
+    "123123",
+    "abc123",
+    "000000",
+    "password1",
+    "iloveyou",
+    "1q2w3e4r",
+    "qwerty123",
+    "dragon",
+    "monkey",
+    "letmein",
+    "football",
+    "baseball",
+    "welcome",
+    "admin",
+    "login",
+    "princess",
+    "sunshine",
+    "master",
+    "shadow",
+    "superman",
+    "trustno1",
+    "michael",
+    "jennifer",
+    "charlie",
+    "ashley",
+    "bailey",
+    "passw0rd",
+    "654321",
+    "666666",
+    "121212",
+    "starwars",
+    "whatever",
+    "freedom",
+    "hello",
+    "secret",
+    "zaq12wsx",
+    "1qaz2wsx",
+    "q1w2e3r4",
+    "asdfgh",
+    "zxcvbnm",
+    "access",
+    "flower",
+    "mustang",
+    "batman",
+    "killer",
+    "hockey",
+    "ranger",
+    "daniel",
+    "jordan",
+    "harley",
+    "thomas",
+    "robert",
+    "soccer",
+    "summer",
+    "love",
+    "computer",
+    "pepper",
+    "ginger",
+    "cheese",
+    "matrix",
+    "buster",
+    "test",
+    "changeme",
+    "default",
+    "root",
+    "guest",
+    "11111111",
+    "987654321",
+    "7777777",
+    "888888",
+    "555555",
+    "696969",
+    "jesus",
+    "ninja",
+    "azerty",
+    "solo",
+    "loveme",
+    "lovely",
+    "nicole",
+    "jessica",
+    "andrew",
+    "joshua",
+    "hannah",
+    "maggie",
+    "tigger",
+    "sophie",
+    "chelsea",
+    "liverpool",
+    "arsenal",
+    "samsung",
+    "google",
+    "hunter",
+    "qazwsx",
+];
+
+const KEYBOARD_ROWS: [&str; 4] = [
+    "`1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+];
+const KEYBOARD_SHIFTED_ROWS: [&str; 4] = [
+    "~!@#$%^&*()_+",
+    "QWERTYUIOP{}|",
+    "ASDFGHJKL:\"",
+    "ZXCVBNM<>?",
+];
+// Number of keys which could start keyboard walk
+const KEYBOARD_STARTING_POSITIONS: f64 = 47.0;
+// Average number of neighbours of a key
+const KEYBOARD_AVERAGE_DEGREE: f64 = 4.6;
+
+// Any pattern is at least this hard to guess, avoids favouring many tiny matches
+const MIN_MATCH_GUESSES: f64 = 50.0;
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    CommonPassword,
+    Dictionary(String),
+    Keyboard(String),
+    Sequence(String),
+    Repeat(String),
+    Date(String),
+    Bruteforce,
+}
+
+#[derive(Debug, Clone)]
+struct Match {
+    start: usize,
+    end: usize,
+    guesses: f64,
+    pattern: Pattern,
+}
+
+pub struct Strength {
+    /// Estimated number of guesses needed to crack the password
+    pub guesses: f64,
+    /// Score from 0 (very weak) to 4 (very strong)
+    pub score: u8,
+    pub warnings: Vec<String>,
+}
+
+impl Strength {
+    pub fn entropy_bits(&self) -> f64 {
+        self.guesses.log2()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.score < 3
+    }
+}
+
+impl std::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}/4), {:.1} bits, ~{:.1e} guesses",
+            self.label(),
+            self.score,
+            self.entropy_bits(),
+            self.guesses
+        )?;
+        if !self.warnings.is_empty() {
+            write!(f, "; {}", self.warnings.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' | '+' => 't',
+        c => c,
+    }
+}
+
+/// Multiplier for capitalization variants of dictionary word.
+fn uppercase_variations(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let all_upper = upper == word.iter().filter(|c| c.is_alphabetic()).count();
+    match upper {
+        0 => 1.0,
+        1 if word[0].is_uppercase() => 2.0,
+        _ if all_upper => 2.0,
+        _ => 10.0,
+    }
+}
+
+/// Rank of common passwords, built once.
+fn common_passwords() -> &'static HashMap<&'static str, usize> {
+    static COMMON: OnceLock<HashMap<&str, usize>> = OnceLock::new();
+    COMMON.get_or_init(|| {
+        COMMON_PASSWORDS
+            .iter()
+            .enumerate()
+            .map(|(rank, word)| (*word, rank + 1))
+            .collect()
+    })
+}
+
+/// Words of passphrase wordlist, built once.
+fn dictionary() -> &'static HashSet<&'static str> {
+    static DICTIONARY: OnceLock<HashSet<&str>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| generator::wordlist().into_iter().collect())
+}
+
+fn dictionary_matches(password: &[char]) -> Vec<Match> {
+    let common = common_passwords();
+    let words = dictionary();
+
+    let mut matches = Vec::new();
+    for start in 0..password.len() {
+        for end in start + 3..=password.len().min(start + 20) {
+            let token = &password[start..end];
+            let lower: String = token.iter().flat_map(|c| c.to_lowercase()).collect();
+            let unleeted: String = lower.chars().map(unleet).collect();
+            let reversed: String = lower.chars().rev().collect();
+            let candidates = [(&lower, 1.0), (&unleeted, 4.0), (&reversed, 2.0)];
+            for (candidate, multiplier) in candidates {
+                let found = if let Some(rank) = common.get(candidate.as_str()) {
+                    Some((*rank as f64, Pattern::CommonPassword))
+                } else if words.contains(candidate.as_str()) {
+                    let pattern = Pattern::Dictionary(candidate.clone());
+                    Some((words.len() as f64, pattern))
+                } else {
+                    None
+                };
+                if let Some((rank, pattern)) = found {
+                    matches.push(Match {
+                        start,
+                        end,
+                        guesses: rank * multiplier * uppercase_variations(token),
+                        pattern,
+                    });
+                    break;
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn keyboard_position(c: char) -> Option<(i32, i32)> {
+    KEYBOARD_ROWS
+        .iter()
+        .chain(KEYBOARD_SHIFTED_ROWS.iter())
+        .enumerate()
+        .find_map(|(row, keys)| {
+            keys.chars()
+                .position(|key| key == c)
+                .map(|col| ((row % KEYBOARD_ROWS.len()) as i32, col as i32))
+        })
+}
+
+/// Direction of step between two keys if they are neighbours on staggered keyboard.
+fn keyboard_step(from: char, to: char) -> Option<(i32, i32)> {
+    let (from_row, from_col) = keyboard_position(from)?;
+    let (to_row, to_col) = keyboard_position(to)?;
+    let step = (to_row - from_row, to_col - from_col);
+    match step {
+        (0, -1) | (0, 1) | (-1, 0) | (-1, 1) | (1, 0) | (1, -1) => Some(step),
+        _ => None,
+    }
+}
+
+fn keyboard_matches(password: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < password.len() {
+        let mut end = start + 1;
+        let mut turns = 0;
+        let mut direction = None;
+        while end < password.len() {
+            match keyboard_step(password[end - 1], password[end]) {
+                Some(step) => {
+                    if direction.is_some_and(|direction| direction != step) {
+                        turns += 1;
+                    }
+                    direction = Some(step);
+                    end += 1;
+                }
+                None => break,
+            }
+        }
+        if end - start >= 4 {
+            let length = (end - start) as f64;
+            let guesses =
+                KEYBOARD_STARTING_POSITIONS * length * KEYBOARD_AVERAGE_DEGREE.powi(turns + 1);
+            matches.push(Match {
+                start,
+                end,
+                guesses,
+                pattern: Pattern::Keyboard(password[start..end].iter().collect()),
+            });
+        }
+        start = end;
+    }
+    matches
+}
+
+fn sequence_and_repeat_matches(password: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < password.len() {
+        let first = password[start];
+        let delta = password
+            .get(start + 1)
+            .map(|next| *next as i64 - first as i64)
+            .unwrap_or(0);
+        let mut end = start + 1;
+        while end < password.len()
+            && delta.abs() <= 1
+            && password[end] as i64 - password[end - 1] as i64 == delta
+            && password[end].is_alphanumeric()
+        {
+            end += 1;
+        }
+        let length = end - start;
+        if length >= 3 {
+            let token: String = password[start..end].iter().collect();
+            let (guesses, pattern) = if delta == 0 {
+                let cardinality = char_cardinality(first);
+                (cardinality * length as f64, Pattern::Repeat(token))
+            } else {
+                let base = if "aAzZ019".contains(first) {
+                    4.0
+                } else if first.is_ascii_digit() {
+                    10.0
+                } else {
+                    26.0
+                };
+                let direction = if delta < 0 { 2.0 } else { 1.0 };
+                (base * direction * length as f64, Pattern::Sequence(token))
+            };
+            matches.push(Match {
+                start,
+                end,
+                guesses,
+                pattern,
+            });
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}
+
+fn year_guesses(year: i32) -> f64 {
+    let current_year = chrono::Local::now().year();
+    ((year - current_year).abs() as f64).max(20.0)
+}
+
+fn parse_year(digits: &str) -> Option<i32> {
+    let year: i32 = digits.parse().ok()?;
+    match digits.len() {
+        2 if year > 50 => Some(1900 + year),
+        2 => Some(2000 + year),
+        4 if (1900..=2099).contains(&year) => Some(year),
+        _ => None,
+    }
+}
+
+fn is_day_month(day: &str, month: &str) -> bool {
+    let valid = |value: &str, max: u32| value.parse::<u32>().is_ok_and(|v| (1..=max).contains(&v));
+    valid(day, 31) && valid(month, 12)
+}
+
+/// Guesses needed for token if it is a date (with or without separators).
+fn date_guesses(token: &str) -> Option<f64> {
+    // parts are sliced by bytes below, dates are ASCII anyway
+    if !token.is_ascii() {
+        return None;
+    }
+    let separators = ['/', '-', '.', ' ', '_'];
+    let parts: Vec<&str> = token.split(separators).collect();
+    let (parts, separator_multiplier) = if parts.len() == 3 {
+        (parts, 4.0)
+    } else if parts.len() == 1 && (token.len() == 6 || token.len() == 8) {
+        let year_len = token.len() - 4;
+        // day/month first or year first
+        let year_last = vec![&token[..2], &token[2..4], &token[4..]];
+        let year_first = vec![
+            &token[..year_len],
+            &token[year_len..year_len + 2],
+            &token[year_len + 2..],
+        ];
+        if parse_year(year_last[2]).is_some()
+            && (is_day_month(year_last[0], year_last[1])
+                || is_day_month(year_last[1], year_last[0]))
+        {
+            (year_last, 1.0)
+        } else {
+            (year_first, 1.0)
+        }
+    } else {
+        return None;
+    };
+    if !parts
+        .iter()
+        .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return None;
+    }
+    let year_last = parse_year(parts[2])
+        .filter(|_| is_day_month(parts[0], parts[1]) || is_day_month(parts[1], parts[0]));
+    let year = match year_last {
+        Some(year) => year,
+        None => parse_year(parts[0]).filter(|_| is_day_month(parts[2], parts[1]))?,
+    };
+    Some(365.0 * year_guesses(year) * separator_multiplier)
+}
+
+fn date_matches(password: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for start in 0..password.len() {
+        if !password[start].is_ascii_digit() {
+            continue;
+        }
+        for end in start + 4..=password.len().min(start + 10) {
+            if !password[end - 1].is_ascii_digit() {
+                continue;
+            }
+            let token: String = password[start..end].iter().collect();
+            let guesses = if token.len() == 4 {
+                parse_year(&token).map(year_guesses)
+            } else {
+                date_guesses(&token)
+            };
+            if let Some(guesses) = guesses {
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses,
+                    pattern: Pattern::Date(token),
+                });
+            }
+        }
+    }
+    matches
+}
+
+fn char_cardinality(c: char) -> f64 {
+    if c.is_ascii_lowercase() || c.is_ascii_uppercase() {
+        26.0
+    } else if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii() {
+        33.0
+    } else {
+        100.0
+    }
+}
+
+/// Size of alphabet which password characters seem to be drawn from.
+fn password_cardinality(password: &[char]) -> f64 {
+    let classes: [fn(&char) -> bool; 5] = [
+        char::is_ascii_lowercase,
+        char::is_ascii_uppercase,
+        char::is_ascii_digit,
+        |c| c.is_ascii() && !c.is_ascii_alphanumeric(),
+        |c| !c.is_ascii(),
+    ];
+    let sizes = [26.0, 26.0, 10.0, 33.0, 100.0];
+    classes
+        .iter()
+        .zip(sizes)
+        .filter(|(class, _)| password.iter().any(class))
+        .map(|(_, size)| size)
+        .sum()
+}
+
+pub fn estimate(password: &str) -> Strength {
+    let password: Vec<char> = password.chars().collect();
+    let cardinality = password_cardinality(&password);
+
+    let mut matches = dictionary_matches(&password);
+    matches.extend(keyboard_matches(&password));
+    matches.extend(sequence_and_repeat_matches(&password));
+    matches.extend(date_matches(&password));
+
+    // best[i] = least guesses needed for first i characters, with last match used
+    let mut best: Vec<(f64, Option<Match>)> = vec![(f64::INFINITY, None); password.len() + 1];
+    best[0].0 = 1.0;
+    for end in 1..=password.len() {
+        let bruteforce = (0..end).map(|start| Match {
+            start,
+            end,
+            guesses: cardinality.powi((end - start) as i32),
+            pattern: Pattern::Bruteforce,
+        });
+        let candidates = matches
+            .iter()
+            .filter(|m| m.end == end)
+            .cloned()
+            .chain(bruteforce);
+        for m in candidates {
+            let guesses = best[m.start].0 * m.guesses.max(MIN_MATCH_GUESSES.min(m.guesses * 10.0));
+            if guesses < best[end].0 {
+                best[end] = (guesses, Some(m));
+            }
+        }
+    }
+
+    let guesses = best[password.len()].0.max(1.0);
+    let mut warnings = Vec::new();
+    let mut end = password.len();
+    while let Some(m) = best[end].1.clone() {
+        let warning = match m.pattern {
+            Pattern::CommonPassword => Some("very common password".to_string()),
+            Pattern::Dictionary(word) => Some(format!("contains dictionary word \"{}\"", word)),
+            Pattern::Keyboard(walk) => Some(format!("contains keyboard pattern \"{}\"", walk)),
+            Pattern::Sequence(seq) => Some(format!("contains sequence \"{}\"", seq)),
+            Pattern::Repeat(rep) => Some(format!("contains repeated characters \"{}\"", rep)),
+            Pattern::Date(date) => Some(format!("contains date or year \"{}\"", date)),
+            Pattern::Bruteforce => None,
+        };
+        warnings.extend(warning);
+        end = m.start;
+    }
+    warnings.reverse();
+    if password.len() < 8 {
+        warnings.insert(0, "too short".to_string());
+    }
+
+    let score = match guesses.log10() {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    };
+    Strength {
+        guesses,
+        score,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_warning(strength: &Strength, warning: &str) -> bool {
+        strength.warnings.iter().any(|w| w.contains(warning))
+    }
+
+    #[test]
+    fn common_password() {
+        let strength = estimate("password");
+        assert_eq!(strength.score, 0);
+        assert!(has_warning(&strength, "very common password"));
+    }
+
+    #[test]
+    fn dictionary_word_variants() {
+        for password in ["4b1l1ty", "ytiliba", "Ability"] {
+            let strength = estimate(password);
+            assert!(strength.is_weak(), "{}", password);
+            assert!(
+                has_warning(&strength, "dictionary word \"ability\""),
+                "{}",
+                password
+            );
+        }
+    }
+
+    #[test]
+    fn keyboard_walk() {
+        let strength = estimate("asdfghjk");
+        assert!(strength.is_weak());
+        assert!(has_warning(&strength, "keyboard pattern \"asdfghjk\""));
+    }
+
+    #[test]
+    fn sequence_and_repeat() {
+        let strength = estimate("abcdefgh");
+        assert_eq!(strength.score, 0);
+        assert!(has_warning(&strength, "sequence \"abcdefgh\""));
+
+        let strength = estimate("zzzzzzzz");
+        assert_eq!(strength.score, 0);
+        assert!(has_warning(&strength, "repeated characters \"zzzzzzzz\""));
+    }
+
+    #[test]
+    fn dates() {
+        for date in ["150389", "15031989"] {
+            let strength = estimate(date);
+            assert!(strength.is_weak(), "{}", date);
+            assert!(has_warning(&strength, "date or year"), "{}", date);
+        }
+    }
+
+    #[test]
+    fn non_ascii() {
+        for password in ["1ü23456", "12€3", "ünïcödé-pässwörd", "€€€€"] {
+            estimate(password);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let strength = estimate("");
+        assert_eq!(strength.score, 0);
+        assert_eq!(strength.guesses, 1.0);
+    }
+
+    #[test]
+    fn random_password() {
+        assert_eq!(estimate("kX9#vQ2$mL7!pR4@").score, 4);
+    }
+}
@@ -0,0 +1,83 @@
+//! Command history persisted per database file
+//! under `$XDG_STATE_HOME/kpcli/history`.
+
+use std::path::{Path, PathBuf};
+
+use rustyline::{Editor, Helper};
+use sha2::{Digest, Sha256};
+
+fn history_dir() -> Option<PathBuf> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_dir.join("kpcli").join("history"))
+}
+
+/// History file of given database, or of sessions without database.
+/// File is named after database file and hash of its absolute path
+/// so that databases with the same name don't share history.
+pub fn history_file(db_path: Option<&Path>) -> Option<PathBuf> {
+    let name = match db_path {
+        Some(db_path) => {
+            let db_path = db_path
+                .canonicalize()
+                .unwrap_or_else(|_| db_path.to_path_buf());
+            let hash = Sha256::digest(db_path.as_os_str().to_string_lossy().as_bytes());
+            let hash: String = hash[..8].iter().map(|b| format!("{:02x}", b)).collect();
+            let stem = db_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            format!("{}-{}", stem, hash)
+        }
+        None => "default".to_string(),
+    };
+    Some(history_dir()?.join(name))
+}
+
+/// Replace history in editor with the one stored in given file.
+pub fn load<H: Helper>(rl: &mut Editor<H>, path: &Path) {
+    rl.clear_history();
+    if path.exists() {
+        if let Err(err) = rl.load_history(path) {
+            eprintln!("Error loading history from {}: {}", path.display(), err);
+        }
+    }
+}
+
+pub fn save<H: Helper>(rl: &mut Editor<H>, path: &Path) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), |dir| {
+            let mut builder = std::fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::DirBuilderExt;
+                builder.mode(0o700);
+            }
+            builder.create(dir)
+        })
+        .map_err(|err| format!("{}", err))
+        .and_then(|_| rl.save_history(path).map_err(|err| format!("{}", err)));
+    if let Err(err) = result {
+        eprintln!("Error saving history to {}: {}", path.display(), err);
+    }
+}
+
+/// Print numbered history entries, or clear history (including the file) if `clear` is set.
+pub fn show_or_clear<H: Helper>(rl: &mut Editor<H>, path: Option<&Path>, clear: bool) {
+    if clear {
+        rl.clear_history();
+        if let Some(path) = path.filter(|path| path.exists()) {
+            if let Err(err) = std::fs::remove_file(path) {
+                eprintln!("Error removing {}: {}", path.display(), err);
+            }
+        }
+        return;
+    }
+    for (i, line) in rl.history().iter().enumerate() {
+        println!("{:>5}  {}", i + 1, line);
+    }
+}
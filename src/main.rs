@@ -1,52 +1,78 @@
 mod executor;
+mod history;
 mod opt;
 
 use clap::Parser;
-use executor::{Command, EditorHelper, Executor, PasswordInput};
-use keepass::DatabaseKey;
-use keepass::{error::DatabaseOpenError, Database};
+use executor::{build_key, Command, Db, EditorHelper, Executor, PasswordInput};
 use opt::Opts;
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
-use std::fs::File;
-use std::path::PathBuf;
+use rustyline::{CompletionType, Config, Editor};
+use std::path::Path;
+use zeroize::Zeroizing;
 
-fn open_db(file: &PathBuf, password: &str) -> Result<Database, DatabaseOpenError> {
-    let mut file = File::open(file)?;
-    Database::open(&mut file, DatabaseKey::new().with_password(password))
+fn open_db(file: &Path, password: &str, keyfile: Option<&Path>) -> Result<Db, String> {
+    let key = build_key(Some(password), keyfile)?;
+    Db::open(file.to_path_buf(), key)
 }
 
-fn main() -> Result<(), DatabaseOpenError> {
+fn main() {
     let opts = Opts::parse();
 
     // Open KeePass database if file was given in cmdline
-    let db: Option<Database> = if let Some(ref file) = opts.db_file {
-        let password = match opts.password {
-            Some(password) => password,
-            None => {
+    let db: Option<Db> = if opts.create {
+        None
+    } else if let Some(ref file) = opts.db_file {
+        let password = match opts.read_password() {
+            Ok(Some(password)) => password,
+            Ok(None) => {
                 let mut rl = Editor::new().unwrap();
                 rl.set_helper(Some(PasswordInput));
                 match rl.readline("Enter password: ") {
-                    Ok(line) => line,
+                    Ok(line) => Zeroizing::new(line),
                     Err(err) => {
                         eprintln!("Error reading line: {}", err);
-                        return Ok(());
+                        return;
                     }
                 }
             }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
         };
-        Some(open_db(file, &password)?)
+        match open_db(file, &password, opts.keyfile.as_deref()) {
+            Ok(db) => Some(db),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
     } else {
         None
     };
 
     let mut executor = Executor::new(db);
+    if let (true, Some(path)) = (opts.create, opts.db_file) {
+        let command = Command::CreateDB {
+            path,
+            keyfile: opts.keyfile,
+        };
+        if let Err(err) = executor.execute(command, &mut EditorHelper::new()) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
 
     if let Some(cmd) = opts.command {
         if let Err(err) = executor.execute(cmd, &mut EditorHelper::new()) {
             eprintln!("{}", err);
         };
-        return Ok(());
+        // there is no way to save changes made by a single command
+        if executor.has_unsaved_changes() {
+            eprintln!("Database has unsaved changes which were discarded! Use interactive session to change database.");
+            std::process::exit(1);
+        }
+        return;
     }
 
     println!("\nType 'help' for a description of available commands.");
@@ -56,8 +82,27 @@ fn main() -> Result<(), DatabaseOpenError> {
     if let Some(db) = executor.get_db() {
         editor_helper.create_db_entries(db);
     }
-    let mut rl = Editor::new().unwrap();
+    // list mode completes unique candidate right away so that
+    // nested paths can be completed by pressing tab repeatedly
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .max_history_size(opts.history_size.max(1))
+        .history_ignore_dups(true)
+        .history_ignore_space(true)
+        .build();
+    let mut rl = Editor::with_config(config).unwrap();
     rl.set_helper(Some(editor_helper));
+    let persist_history = opts.history_size > 0;
+    let mut history_file = None;
+    if persist_history {
+        history_file = history::history_file(executor.get_db_path());
+        if let Some(path) = &history_file {
+            history::load(&mut rl, path);
+        }
+    }
+    // Set once user was warned about unsaved changes so that
+    // second CTRL-C/CTRL-D quits without saving
+    let mut warned_unsaved = false;
     loop {
         let readline = if let Some(curr_group) = &executor.get_current_group_name() {
             rl.readline(&format!("{}>> ", curr_group))
@@ -66,24 +111,56 @@ fn main() -> Result<(), DatabaseOpenError> {
         };
         match readline {
             Ok(line) => {
+                warned_unsaved = false;
                 let command = match Command::try_parse(&line) {
                     Err(err) => {
-                        err.print();
+                        let _ = err.print();
                         continue;
                     }
                     Ok(cmd) => cmd,
                 };
-                rl.add_history_entry(line.as_str());
+                if !command.contains_secret() {
+                    rl.add_history_entry(line.as_str());
+                }
+                if let Command::History { clear } = command {
+                    history::show_or_clear(&mut rl, history_file.as_deref(), clear);
+                    continue;
+                }
                 if let Err(err) = executor.execute(command, rl.helper_mut().unwrap()) {
                     eprintln!("{}", err);
                 };
+                // switch history when other database was opened or closed
+                let current_file = history::history_file(executor.get_db_path());
+                if persist_history && current_file != history_file {
+                    if let Some(path) = &history_file {
+                        history::save(&mut rl, path);
+                    }
+                    history_file = current_file;
+                    if let Some(path) = &history_file {
+                        history::load(&mut rl, path);
+                    }
+                }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
+                if executor.has_unsaved_changes() && !warned_unsaved {
+                    eprintln!(
+                        "Database has unsaved changes! Press CTRL-C again to quit without saving."
+                    );
+                    warned_unsaved = true;
+                    continue;
+                }
                 break;
             }
             Err(ReadlineError::Eof) => {
                 println!("CTRL-D");
+                if executor.has_unsaved_changes() && !warned_unsaved {
+                    eprintln!(
+                        "Database has unsaved changes! Press CTRL-D again to quit without saving."
+                    );
+                    warned_unsaved = true;
+                    continue;
+                }
                 break;
             }
             Err(err) => {
@@ -92,5 +169,7 @@ fn main() -> Result<(), DatabaseOpenError> {
             }
         }
     }
-    Ok(())
+    if let Some(path) = &history_file {
+        history::save(&mut rl, path);
+    }
 }
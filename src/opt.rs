@@ -1,9 +1,16 @@
 use crate::executor::Command;
-use clap::Parser;
+use clap::{ArgGroup, Parser};
+use std::fs::File;
+use std::io::Read;
 use std::path::PathBuf;
+use std::process::{self, Stdio};
+use zeroize::{Zeroize, Zeroizing};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
+#[command(group(
+    ArgGroup::new("password_source").args(["password_file", "password_fd", "password_command"])
+))]
 pub struct Opts {
     #[arg(long)]
     pub db_file: Option<PathBuf>,
@@ -11,7 +18,122 @@ pub struct Opts {
     #[arg(short, long, env = "DB_PASSWORD")]
     pub password: Option<String>,
 
+    /// Read password from first line of given file.
+    /// Takes precedence over --password and DB_PASSWORD.
+    #[arg(long)]
+    pub password_file: Option<PathBuf>,
+
+    /// Read password from first line read from given file descriptor (not 1 or 2).
+    /// Takes precedence over --password and DB_PASSWORD.
+    #[arg(long)]
+    pub password_fd: Option<i32>,
+
+    /// Read password from first line of standard output of given shell command.
+    /// Takes precedence over --password and DB_PASSWORD.
+    #[arg(long)]
+    pub password_command: Option<String>,
+
+    /// Key file used together with (or instead of) password.
+    #[arg(short, long)]
+    pub keyfile: Option<PathBuf>,
+
+    /// Create new database at db_file instead of opening it.
+    /// Password is read interactively.
+    #[arg(long, requires = "db_file")]
+    pub create: bool,
+
+    /// Maximum number of commands kept in history,
+    /// 0 disables storing history on disk.
+    #[arg(long, env = "KPCLI_HISTORY_SIZE", default_value_t = 1000)]
+    pub history_size: usize,
+
     /// Optionally run single command and exit (no interactive session).
     #[command(subcommand)]
     pub command: Option<Command>,
 }
+
+impl Opts {
+    /// Read password from non-interactive source given in cmdline, if any.
+    pub fn read_password(&self) -> Result<Option<Zeroizing<String>>, String> {
+        let data = if let Some(path) = &self.password_file {
+            let mut file = File::open(path)
+                .map_err(|err| format!("Error opening {}: {}", path.display(), err))?;
+            read_to_end(&mut file)
+                .map_err(|err| format!("Error reading {}: {}", path.display(), err))?
+        } else if let Some(fd) = self.password_fd {
+            read_fd(fd)?
+        } else if let Some(command) = &self.password_command {
+            run_password_command(command)?
+        } else if let Some(password) = &self.password {
+            return Ok(Some(Zeroizing::new(password.clone())));
+        } else {
+            return Ok(None);
+        };
+        into_password(data).map(Some)
+    }
+}
+
+fn read_to_end(reader: &mut dyn Read) -> std::io::Result<Zeroizing<Vec<u8>>> {
+    // preallocate so that password is not left behind in reallocated buffers
+    let mut data = Zeroizing::new(Vec::with_capacity(4096));
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<Zeroizing<Vec<u8>>, String> {
+    if fd == 1 || fd == 2 {
+        return Err(format!(
+            "Refusing to read password from output descriptor {}!",
+            fd
+        ));
+    }
+    // opened by path so that unopened descriptors are reported as errors
+    // and descriptor itself is never closed
+    File::open(format!("/dev/fd/{}", fd))
+        .and_then(|mut file| read_to_end(&mut file))
+        .map_err(|err| format!("Error reading file descriptor {}: {}", fd, err))
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<Zeroizing<Vec<u8>>, String> {
+    Err("--password-fd is supported only on unix!".to_string())
+}
+
+fn run_password_command(command: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = process::Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| format!("Error running password command: {}", err))?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        return Err(format!("Password command exited with {}", output.status));
+    }
+    Ok(stdout)
+}
+
+/// Convert raw bytes into password, only trailing newline is stripped.
+/// First line of data, so that e.g. output of `pass show` can be used.
+fn into_password(mut data: Zeroizing<Vec<u8>>) -> Result<Zeroizing<String>, String> {
+    // truncated bytes are still zeroed when data is dropped
+    if let Some(end) = data.iter().position(|b| *b == b'\n') {
+        data.truncate(end);
+        if data.ends_with(b"\r") {
+            data.pop();
+        }
+    }
+    String::from_utf8(std::mem::take(&mut *data))
+        .map(Zeroizing::new)
+        .map_err(|err| {
+            err.into_bytes().zeroize();
+            "Password is not valid UTF-8!".to_string()
+        })
+}